@@ -1,8 +1,9 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use log::LevelFilter;
 use smart_default::SmartDefault;
 use std::path::PathBuf;
 
-use crate::config::default_config_path;
+use crate::{config::default_config_path, control::Request, instance::DEFAULT_INSTANCE};
 
 #[derive(Debug, Parser, SmartDefault)]
 #[command(author, version, about)]
@@ -14,4 +15,70 @@ pub struct Args {
     )]
     /// Path to config file
     pub config_path: PathBuf,
+
+    #[arg(long, global = true, value_parser = parse_instance)]
+    /// Name of the instance, to run several at once (default: "default"). Commands
+    /// that refresh running instances refresh all of them unless this is given
+    pub instance: Option<String>,
+
+    #[arg(long, global = true)]
+    /// Log level: off, error, warn, info, debug or trace (default: the log_level setting)
+    pub log_level: Option<LevelFilter>,
+
+    #[command(subcommand)]
+    pub command: Option<SubCommand>,
+}
+
+impl Args {
+    /// The instance name, or the default one.
+    pub fn instance_name(&self) -> &str {
+        self.instance.as_deref().unwrap_or(DEFAULT_INSTANCE)
+    }
+}
+
+// instance names are used as file names
+fn parse_instance(name: &str) -> Result<String, String> {
+    match name.is_empty() || name.contains('/') {
+        true => Err("must be non-empty and can't contain '/'".to_string()),
+        false => Ok(name.to_string()),
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SubCommand {
+    /// Run a component action (e.g. brightness_up) and refresh running instances
+    Action {
+        /// Name of the action, e.g. brightness_up, brightness_down, set_brightness
+        action: String,
+
+        /// Arguments to the action, e.g. a percent for set_brightness
+        args: Vec<String>,
+    },
+
+    /// Control Timer components and refresh running instances
+    Timer {
+        #[arg(value_parser = ["start", "pause", "toggle", "reset"])]
+        action: String,
+    },
+
+    /// Refresh components in running instances, through their control sockets
+    Signal {
+        /// Component name (its `name` setting, or e.g. time) or index
+        component: String,
+    },
+
+    /// Send a command to the running instance through its control socket
+    Ctl {
+        #[command(subcommand)]
+        request: Request,
+    },
+
+    /// Translate another status bar's config into ferristatus YAML (printed to stdout)
+    Import {
+        #[arg(value_parser = ["i3blocks", "dwmblocks", "slstatus"])]
+        format: String,
+
+        /// Path to the config file to translate
+        file: PathBuf,
+    },
 }
@@ -2,14 +2,19 @@ mod alsa;
 mod backlight;
 mod battery;
 mod command;
+mod load;
+mod power;
+mod temperature;
 mod text;
 mod time;
+mod timer;
+mod uptime;
 
 use core::fmt;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt::{Debug, Display},
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, mpsc::Sender},
     time::{Duration, Instant},
 };
 
@@ -18,11 +23,21 @@ use anyhow::Context;
 use backlight::Backlight;
 use battery::Battery;
 use command::Command;
+use load::Load;
+use power::Power;
 use serde::{Deserialize, Deserializer};
 use serde_yml::Value;
 use smart_default::SmartDefault;
+use temperature::Temperature;
 use text::Text;
 use time::Time;
+use timer::Timer;
+use uptime::Uptime;
+
+use crate::{
+    errors::MyErrors,
+    watch::{ClockGranularity, WatchTargets},
+};
 
 ///////////////////////////////////////////////////////////////////////////////
 //                              Component Traits                             //
@@ -57,25 +72,78 @@ pub trait Component: Debug {
 
         Ok(())
     }
-    fn update_check(&self) -> anyhow::Result<bool> {
+    /// When this component is next due for an update, or None if never.
+    fn next_update(&self) -> anyhow::Result<Option<Instant>> {
         let last_updated: &Instant = match self.get_last_updated()? {
             Some(v) => v,
-            None => return Ok(true),
+            None => return Ok(Some(Instant::now())),
         };
         let interval = Duration::from_millis(*self.get_refresh_interval()?);
-        let elapsed = last_updated.elapsed();
-        Ok(elapsed > interval)
+        Ok(Some(*last_updated + interval))
+    }
+    fn update_check(&self) -> anyhow::Result<bool> {
+        Ok(self
+            .next_update()?
+            .is_some_and(|deadline| deadline <= Instant::now()))
     }
+    /// Update, returning whether the cache changed.
+    fn update_and_compare(&mut self) -> anyhow::Result<bool> {
+        let before: Option<String> = self.get_cache()?.map(|s| s.to_string());
+        self.update()?;
+        Ok(self.get_cache()? != before.as_deref())
+    }
+    /// Update if due, returning whether the cache changed.
     fn update_maybe(&mut self) -> anyhow::Result<bool> {
         match self.update_check()? {
-            true => {
-                self.update()?;
-                Ok(true)
-            }
+            true => self.update_and_compare(),
             false => Ok(false),
         }
     }
 
+    /// Run a named action (e.g. "brightness_up"), returning whether this component
+    /// supports it.
+    fn run_action(&mut self, _action: &str, _args: &[String]) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    /// The action to run when SIGRTMIN+`signal` is received, if any.
+    fn get_signal_action(&self, _signal: u32) -> anyhow::Result<Option<&str>> {
+        Ok(None)
+    }
+
+    /// Every signal that `get_signal_action` has an action for.
+    fn get_action_signals(&self) -> anyhow::Result<Vec<u32>> {
+        Ok(Vec::new())
+    }
+
+    /// What to watch for changes, if the component is in watch mode.
+    fn get_watch_targets(&self) -> anyhow::Result<Option<WatchTargets>> {
+        Ok(None)
+    }
+
+    /// Tell the component its name (the `name` setting, or else its kind), once
+    /// it's been created.
+    fn set_name(&mut self, _name: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Replace the output with `text`, returning whether this component supports it.
+    fn set_text(&mut self, _text: &str) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    /// Start any background threads the component needs. They send
+    /// `signals::WAKE` through `sender` when the component has new output, so
+    /// `next_update` should then report it as due.
+    fn spawn_background(&mut self, _sender: Sender<u32>) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Wall-clock boundaries to update at, if the component is aligned to the clock.
+    fn get_clock_granularity(&self) -> anyhow::Result<Option<ClockGranularity>> {
+        Ok(None)
+    }
+
     fn get_last_updated(&self) -> anyhow::Result<&Option<std::time::Instant>>;
     fn get_refresh_interval(&self) -> anyhow::Result<&u64>;
     fn get_signal_value(&self) -> anyhow::Result<Option<&u32>>;
@@ -106,6 +174,10 @@ pub type ComponentVecType = Vec<ComponentType>;
 pub struct ComponentVec {
     #[default(Vec::new())]
     pub vec: ComponentVecType,
+    /// The name of each component in `vec`: its `name` setting, or else its
+    /// kind, e.g. "time".
+    #[default(Vec::new())]
+    pub names: Vec<String>,
 }
 
 macro_rules! create_component_from_name {
@@ -135,27 +207,223 @@ impl<'de> Deserialize<'de> for ComponentVec {
             .flat_map(|map| map.into_iter())
             .collect();
 
+        parse_components(components_flattened).map_err(|e| {
+            serde::de::Error::custom(format!("could not parse settings for component: {}", e))
+        })
+    }
+}
+
+fn build_component(kind: &str, value: &Value, name: &str) -> anyhow::Result<ComponentType> {
+    let component: anyhow::Result<ComponentType> = create_component_from_name!(
+        kind, value,
+        "alsa" => Alsa,
+        "backlight" => Backlight,
+        "battery" => Battery,
+        "load" => Load,
+        "power" => Power,
+        "temperature" => Temperature,
+        "text" => Text,
+        "time" => Time,
+        "timer" => Timer,
+        "uptime" => Uptime,
+        "command" => Command,
+    );
+    let component = component?;
+    component
+        .lock()
+        .map_err(MyErrors::from_poison_error)?
+        .set_name(name)?;
+
+    Ok(component)
+}
+
+fn parse_components(components_flattened: Vec<(String, Value)>) -> anyhow::Result<ComponentVec> {
+    let mut components = ComponentVec::default();
+    let mut parsed: Vec<(String, Value, bool)> = Vec::new();
+
+    for (kind, mut value) in components_flattened {
+        // `name` is accepted by every component, so it's not part of their settings
+        let name = take_name(&mut value)?;
+        anyhow::ensure!(
+            name.as_ref()
+                .is_none_or(|n| !components.names.iter().any(|m| m.eq_ignore_ascii_case(n))),
+            "the name {} is used twice",
+            name.unwrap_or_default()
+        );
+        let explicit_signal = value.as_mapping().is_some_and(|m| m.contains_key("signal"));
+
         // Parse each component
-        let components_new: ComponentVecType = components_flattened
-            .iter()
-            .map(|(name, value)| -> anyhow::Result<ComponentType> {
-                create_component_from_name!(
-                    name, value,
-                    "alsa" => Alsa,
-                    "backlight" => Backlight,
-                    "battery" => Battery,
-                    "text" => Text,
-                    "time" => Time,
-                    "command" => Command,
-                )
-            })
-            .collect::<Result<_, anyhow::Error>>()
-            .map_err(|e| {
-                serde::de::Error::custom(format!("could not parse settings for component: {}", e))
-            })?;
-
-        Ok(ComponentVec {
-            vec: components_new,
+        let name = name.unwrap_or_else(|| kind.to_lowercase());
+        components.vec.push(build_component(&kind, &value, &name)?);
+        components.names.push(name);
+        parsed.push((kind, value, explicit_signal));
+    }
+
+    assign_signals(&mut components, &mut parsed)?;
+
+    Ok(components)
+}
+
+/// Make sure every component has its own signal, in the range of RT signals.
+/// Signals that were set explicitly, including action signals, must be unique.
+/// Components whose default signal is already taken (e.g. a second Command)
+/// get the lowest free one.
+fn assign_signals(
+    components: &mut ComponentVec,
+    parsed: &mut [(String, Value, bool)],
+) -> anyhow::Result<()> {
+    let max = (libc::SIGRTMAX() - libc::SIGRTMIN()) as u32;
+
+    let signals: Vec<Option<u32>> = components
+        .vec
+        .iter()
+        .map(|c| -> anyhow::Result<Option<u32>> {
+            let c_guard = c.lock().map_err(MyErrors::from_poison_error)?;
+            Ok(c_guard.get_signal_value()?.copied())
         })
+        .collect::<anyhow::Result<_>>()?;
+
+    // explicit and action signals first, so defaults make way for them
+    let mut taken: BTreeMap<u32, usize> = BTreeMap::new();
+    for (i, signal) in signals.iter().enumerate() {
+        let action_signals = components.vec[i]
+            .lock()
+            .map_err(MyErrors::from_poison_error)?
+            .get_action_signals()?;
+        let explicit = signal.filter(|_| parsed[i].2);
+
+        for signal in explicit.into_iter().chain(action_signals) {
+            anyhow::ensure!(
+                signal <= max,
+                "{}: signal {} is out of range (0 to {})",
+                components.names[i],
+                signal,
+                max
+            );
+            match taken.insert(signal, i) {
+                Some(other) if other == i => {
+                    anyhow::bail!("{}: signal {} is used twice", components.names[i], signal)
+                }
+                Some(other) => anyhow::bail!(
+                    "{} and {} both have signal {}, every component needs its own",
+                    components.names[other],
+                    components.names[i],
+                    signal
+                ),
+                None => {}
+            }
+        }
+    }
+
+    let mut colliding: Vec<usize> = Vec::new();
+    for (i, signal) in signals.iter().enumerate() {
+        let (Some(signal), false) = (signal, parsed[i].2) else {
+            continue;
+        };
+        if taken.contains_key(signal) {
+            colliding.push(i);
+        } else {
+            taken.insert(*signal, i);
+        }
+    }
+
+    for i in colliding {
+        let signal = (0..=max)
+            .find(|s| !taken.contains_key(s))
+            .context("ran out of signals, there are more components than RT signals")?;
+        log::info!("{} gets signal {}", components.names[i], signal);
+
+        let (kind, value, _) = &mut parsed[i];
+        if value.is_null() {
+            *value = Value::Mapping(Default::default());
+        }
+        value
+            .as_mapping_mut()
+            .context("expected the component's settings")?
+            .insert("signal".into(), signal.into());
+        components.vec[i] = build_component(kind, value, &components.names[i])?;
+        taken.insert(signal, i);
+    }
+
+    Ok(())
+}
+
+/// Remove and return the `name` setting of a component, if it has one.
+fn take_name(value: &mut Value) -> anyhow::Result<Option<String>> {
+    let Some(name) = value.as_mapping_mut().and_then(|m| m.remove("name")) else {
+        return Ok(None);
+    };
+    let name = name.as_str().context("name must be a string")?.to_string();
+
+    anyhow::ensure!(
+        !name.is_empty() && !name.contains(char::is_whitespace),
+        "invalid name {:?}: it can't be empty or contain spaces",
+        name
+    );
+    // numbers are taken by indices, e.g. `ferristatus ctl get 3`
+    anyhow::ensure!(
+        name.parse::<usize>().is_err(),
+        "invalid name {:?}: it can't be a number",
+        name
+    );
+
+    Ok(Some(name))
+}
+
+/// Indices of the components called `component` (by `name`, or by kind like
+/// "time" when unnamed), or the one at that index.
+pub fn find_components(names: &[String], component: &str) -> anyhow::Result<Vec<usize>> {
+    if let Ok(i) = component.parse::<usize>() {
+        anyhow::ensure!(i < names.len(), "no component at index {}", i);
+        return Ok(vec![i]);
+    }
+
+    let found: Vec<usize> = (0..names.len())
+        .filter(|&i| names[i].eq_ignore_ascii_case(component))
+        .collect();
+    anyhow::ensure!(!found.is_empty(), "no component named {}", component);
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_names() -> anyhow::Result<()> {
+        let components: ComponentVec = serde_yml::from_str(
+            "[{time: {name: clock}}, {text: '|'}, {command: {shell_command: date}}]",
+        )?;
+        assert_eq!(components.names, ["clock", "text", "command"]);
+        assert_eq!(find_components(&components.names, "Clock")?, [0]);
+
+        for invalid in [
+            "[{time: {name: a}}, {uptime: {name: a}}]",
+            "[{time: {name: '3'}}]",
+            "[{time: {signal: 99}}]",
+            "[{time: {signal: 3}}, {uptime: {signal: 3}}]",
+            "[{timer: {action_signals: {timer_reset: 99}}}]",
+            "[{timer: {action_signals: {timer_strat: 20}}}]",
+            "[{timer: {signal: 20, action_signals: {timer_reset: 20}}}]",
+            "[{time: {signal: 20}}, {timer: {action_signals: {timer_reset: 20}}}]",
+        ] {
+            assert!(serde_yml::from_str::<ComponentVec>(invalid).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn assigns_free_signals() -> anyhow::Result<()> {
+        let components: ComponentVec = serde_yml::from_str(
+            "[{command: {shell_command: a}}, {command: {shell_command: b}}, {time: {signal: 0}}, {uptime: }, {timer: {action_signals: {timer_toggle: 1}}}]",
+        )?;
+        let signals: Vec<u32> = components
+            .vec
+            .iter()
+            .map(|c| Ok(*c.lock().unwrap().get_signal_value()?.context("no signal")?))
+            .collect::<anyhow::Result<_>>()?;
+        // the second command gets the lowest signal not taken
+        assert_eq!(signals, [8, 2, 0, 12, 14]);
+        Ok(())
     }
 }
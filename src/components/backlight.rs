@@ -1,11 +1,19 @@
-use std::{fs, path::PathBuf, time};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time,
+};
 
 use anyhow::Context;
 use serde::Deserialize;
 use serde_yml::Value;
 use smart_default::SmartDefault;
 
-use crate::{apply_strfmt, impl_component_methods, new_from_value, utils::find_current_level};
+use crate::{
+    apply_strfmt, impl_component_methods, new_from_value,
+    utils::{PathsSetting, find_current_level, list_sysfs_class, read_sysfs_attr},
+    watch::WatchTargets,
+};
 
 use super::Component;
 
@@ -19,7 +27,14 @@ pub struct Backlight {
 
 #[derive(Debug, SmartDefault)]
 pub struct BacklightState {
+    /// Percent of the first device.
     pub percent: Option<i32>,
+    /// Percent of every device, `None` if it couldn't be read.
+    pub percents: Vec<Option<i32>>,
+    /// Devices in use, `None` until the first update.
+    pub paths: Option<Vec<PathBuf>>,
+    /// Devices that couldn't be read on the last update.
+    pub unreadable: Vec<PathBuf>,
     pub last_updated: Option<time::Instant>,
     pub cache: Option<String>,
 }
@@ -33,8 +48,26 @@ pub struct BacklightSettings {
     #[default(6)]
     pub signal: u32,
 
-    #[default(PathBuf::from("/sys/class/backlight/acpi_video0"))]
-    pub path: PathBuf,
+    /// A backlight sysfs entry, a list of them, or "auto" to pick one by type.
+    #[default(PathsSetting::One(PathBuf::from("auto")))]
+    pub path: PathsSetting,
+
+    /// Also update immediately when the brightness changes (inotify and uevents).
+    #[default(false)]
+    pub watch: bool,
+
+    /// Percent to change by for the brightness_up and brightness_down actions.
+    #[default(5)]
+    pub step: u32,
+
+    /// Lowest percent the brightness actions will go down to.
+    #[default(1)]
+    pub min_percent: u32,
+
+    /// Exponent of the brightness curve. 1.0 is linear, higher values give finer
+    /// control at low brightness (2.0 to 3.0 roughly matches perceived brightness).
+    #[default(1.0)]
+    pub exponent: f32,
 
     #[default(BacklightFormatSettings::default())]
     pub format: BacklightFormatSettings,
@@ -50,30 +83,85 @@ pub struct BacklightFormatSettings {
     pub levels: Option<Vec<(i32, String)>>,
 }
 
+impl Backlight {
+    fn resolve_paths(&self) -> Vec<PathBuf> {
+        match self.settings.path.is_auto() {
+            true => discover_backlight().into_iter().collect(),
+            false => self.settings.path.paths().to_vec(),
+        }
+    }
+
+    /// Set every device to `percent` (on the configured curve), respecting `min_percent`.
+    fn set_percent(&self, percent: impl Fn(f32) -> f32) -> anyhow::Result<()> {
+        let paths = self.resolve_paths();
+        anyhow::ensure!(!paths.is_empty(), "no backlight device found");
+
+        for path in paths {
+            let current = read_percent(&path, self.settings.exponent)?;
+            let target = percent(current);
+            anyhow::ensure!(target.is_finite(), "invalid brightness: {}", target);
+            let target = target.clamp(self.settings.min_percent as f32, 100.0);
+            // on a coarse device the floor can round down to 0, which turns it off
+            let min_brightness = (self.settings.min_percent > 0) as u64;
+            write_percent(&path, target, self.settings.exponent, min_brightness)?;
+            log::info!("set backlight {} to {}%", path.display(), target.round());
+        }
+
+        Ok(())
+    }
+}
+
 impl Component for Backlight {
     fn new_from_value(value: &Value) -> anyhow::Result<Self>
     where
         Self: std::marker::Sized,
     {
-        new_from_value!(
+        let backlight: anyhow::Result<Self> = new_from_value!(
             value => BacklightSettings,
             sort_levels: true
-        )
+        );
+        let backlight = backlight?;
+        anyhow::ensure!(
+            backlight.settings.min_percent <= 100,
+            "min_percent must be at most 100, got {}",
+            backlight.settings.min_percent
+        );
+        anyhow::ensure!(
+            backlight.settings.exponent.is_finite() && backlight.settings.exponent > 0.0,
+            "exponent must be greater than 0, got {}",
+            backlight.settings.exponent
+        );
+
+        Ok(backlight)
     }
 
     fn update_state(&mut self) -> anyhow::Result<()> {
-        let path = &self.settings.path;
-        let brightness: f32 = fs::read_to_string(path.join("brightness"))
-            .context("failed to read file brightness")?
-            .trim()
-            .parse()?;
-        let max_brightness: f32 = fs::read_to_string(path.join("max_brightness"))
-            .context("failed to read file max_brightness")?
-            .trim()
-            .parse()?;
-        let percent = ((brightness * 100.0) / max_brightness).round() as i32;
-
-        self.state.percent = Some(percent);
+        let paths: Vec<PathBuf> = self.resolve_paths();
+        if self.state.paths.as_ref() != Some(&paths) {
+            match paths.as_slice() {
+                [] => log::warn!("no backlight device found"),
+                _ => log::info!("using backlight devices: {:?}", paths),
+            }
+        }
+
+        // warn once for each device that becomes unreadable
+        let mut unreadable: Vec<PathBuf> = Vec::new();
+        self.state.percents = paths
+            .iter()
+            .map(|p| match read_percent(p, self.settings.exponent) {
+                Ok(v) => Some(v.round() as i32),
+                Err(e) => {
+                    if !self.state.unreadable.contains(p) {
+                        log::warn!("failed to read backlight {}: {:#}", p.display(), e);
+                    }
+                    unreadable.push(p.clone());
+                    None
+                }
+            })
+            .collect();
+        self.state.unreadable = unreadable;
+        self.state.percent = self.state.percents.first().copied().flatten();
+        self.state.paths = Some(paths);
         self.state.last_updated = Some(time::Instant::now());
 
         Ok(())
@@ -96,8 +184,18 @@ impl Component for Backlight {
     }
 
     fn apply_strfmt_template(&self, template: &str) -> anyhow::Result<Option<String>> {
+        // per-device variables, e.g. {p0}, {p1}
+        let per_device = self.state.percents.iter().enumerate().map(|(i, p)| {
+            let value = match p {
+                Some(v) => v.to_string(),
+                None => "N/A".to_string(),
+            };
+            (format!("p{}", i), value)
+        });
+
         apply_strfmt!(
             template,
+            extra: per_device,
             "p" => match self.state.percent {
                 Some(v) => v.to_string(),
                 None => "N/A".to_string(),
@@ -105,6 +203,33 @@ impl Component for Backlight {
         )
     }
 
+    fn run_action(&mut self, action: &str, args: &[String]) -> anyhow::Result<bool> {
+        let step = self.settings.step as f32;
+        match action {
+            "brightness_up" => self.set_percent(|current| current + step)?,
+            "brightness_down" => self.set_percent(|current| current - step)?,
+            "set_brightness" => {
+                let target: f32 = args
+                    .first()
+                    .context("set_brightness requires a percent")?
+                    .trim_end_matches('%')
+                    .parse()
+                    .context("failed to parse percent for set_brightness")?;
+                self.set_percent(|_| target)?
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn get_watch_targets(&self) -> anyhow::Result<Option<WatchTargets>> {
+        Ok(self.settings.watch.then(|| WatchTargets {
+            devices: self.resolve_paths(),
+            file: "brightness",
+            subsystem: "backlight",
+        }))
+    }
+
     impl_component_methods!(
         set_cache,
         get_last_updated,
@@ -114,3 +239,50 @@ impl Component for Backlight {
         default_output
     );
 }
+
+fn read_brightness_file(path: &Path, name: &str) -> anyhow::Result<f32> {
+    Ok(fs::read_to_string(path.join(name))
+        .with_context(|| format!("failed to read file {}", name))?
+        .trim()
+        .parse()?)
+}
+
+/// Read the brightness as a percent on the curve given by `exponent`.
+fn read_percent(path: &Path, exponent: f32) -> anyhow::Result<f32> {
+    let brightness = read_brightness_file(path, "brightness")?;
+    let max_brightness = read_brightness_file(path, "max_brightness")?;
+    Ok((brightness / max_brightness).powf(1.0 / exponent) * 100.0)
+}
+
+/// Write a percent on the curve given by `exponent` to the brightness file, but
+/// no less than `min_brightness`.
+fn write_percent(
+    path: &Path,
+    percent: f32,
+    exponent: f32,
+    min_brightness: u64,
+) -> anyhow::Result<()> {
+    let max_brightness = read_brightness_file(path, "max_brightness")?;
+    let brightness = ((percent / 100.0).powf(exponent) * max_brightness).round() as u64;
+    let brightness = brightness.max(min_brightness);
+    fs::write(path.join("brightness"), brightness.to_string()).with_context(|| {
+        format!(
+            "failed to write brightness to {} (is the user allowed to write it?)",
+            path.display()
+        )
+    })
+}
+
+/// Pick the backlight device to use, preferring firmware over platform over raw interfaces
+/// (the same order the kernel documents for userspace).
+fn discover_backlight() -> Option<PathBuf> {
+    let priority = |p: &PathBuf| match read_sysfs_attr(&p.join("type")).as_deref() {
+        Some("firmware") => 0,
+        Some("platform") => 1,
+        Some("raw") => 2,
+        _ => 3,
+    };
+    list_sysfs_class("/sys/class/backlight", "")
+        .into_iter()
+        .min_by_key(priority)
+}
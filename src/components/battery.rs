@@ -1,13 +1,21 @@
-use std::{path::PathBuf, time};
+use std::{
+    path::{Path, PathBuf},
+    time,
+};
 
 use acpi_client::{BatteryInfo, ChargingState};
 use anyhow::Context;
-use once_cell::sync::Lazy;
-use regex::Regex;
 use serde::Deserialize;
 use smart_default::SmartDefault;
 
-use crate::{apply_strfmt, impl_component_methods, utils::find_current_level};
+use crate::{
+    apply_strfmt, impl_component_methods,
+    utils::{
+        PathsSetting, find_current_level, format_duration_trimmed, list_sysfs_class,
+        read_sysfs_attr, read_sysfs_num,
+    },
+    watch::WatchTargets,
+};
 
 use super::Component;
 
@@ -24,10 +32,36 @@ pub struct BatteryState {
     pub percent: Option<i32>,
     pub time: Option<time::Duration>,
     pub charging_state: Option<ChargingState>,
+    pub batteries: Vec<BatteryReading>,
+    pub paths: Vec<PathBuf>,
+    /// Batteries that couldn't be read on the last update, e.g. a removed BAT1.
+    pub unreadable: Vec<PathBuf>,
     pub last_updated: Option<time::Instant>,
     pub cache: Option<String>,
 }
 
+/// The state of a single battery.
+#[derive(Debug, Clone)]
+pub struct BatteryReading {
+    pub percent: f32,
+    pub time: time::Duration,
+    pub charging_state: ChargingState,
+    /// Whether `now`, `full` and `full_design` are energy (µWh) rather than
+    /// charge (µAh).
+    pub energy: bool,
+    /// Current energy (or charge) as reported by sysfs.
+    pub now: Option<f64>,
+    /// Full energy (or charge) as reported by sysfs.
+    pub full: Option<f64>,
+    /// Power (or current) draw as reported by sysfs.
+    pub rate: Option<f64>,
+    /// Design capacity, in the same unit as `full`.
+    pub full_design: Option<f64>,
+    /// Power draw in watts.
+    pub watts: Option<f64>,
+    pub cycles: Option<u32>,
+}
+
 #[derive(Debug, SmartDefault, Deserialize)]
 #[serde(default, deny_unknown_fields)]
 pub struct BatterySettings {
@@ -37,8 +71,13 @@ pub struct BatterySettings {
     #[default(7)]
     pub signal: u32,
 
-    #[default(PathBuf::from("/sys/class/power_supply/BAT0"))]
-    pub path: PathBuf,
+    /// A battery sysfs entry, a list of them, or "auto" for every battery.
+    #[default(PathsSetting::One(PathBuf::from("/sys/class/power_supply/BAT0")))]
+    pub path: PathsSetting,
+
+    /// Also update immediately when the kernel reports a change (uevents).
+    #[default(false)]
+    pub watch: bool,
 
     #[default(BatteryFormatSettings::default())]
     pub format: BatteryFormatSettings,
@@ -63,6 +102,48 @@ pub struct BatteryFormatSettings {
     pub discharging: Option<Vec<(i32, String)>>,
 }
 
+impl Battery {
+    fn resolve_paths(&self) -> Vec<PathBuf> {
+        match self.settings.path.is_auto() {
+            true => discover_batteries(),
+            false => self.settings.path.paths().to_vec(),
+        }
+    }
+
+    /// Combined power draw of all batteries, in watts.
+    fn watts(&self) -> Option<f64> {
+        let batteries = &self.state.batteries;
+        batteries
+            .iter()
+            .map(|b| b.watts)
+            .sum::<Option<f64>>()
+            .filter(|_| !batteries.is_empty())
+    }
+
+    /// Combined full capacity as a percent of the combined design capacity, or
+    /// the average health if the batteries report different units.
+    fn health(&self) -> Option<f64> {
+        let batteries = &self.state.batteries;
+        if !same_unit(batteries) {
+            let healths: Vec<f64> = batteries
+                .iter()
+                .map(|b| Some(b.full? / b.full_design?))
+                .collect::<Option<_>>()?;
+            return Some(healths.iter().sum::<f64>() / healths.len() as f64 * 100.0);
+        }
+
+        let (full, design) = batteries.iter().try_fold((0.0, 0.0), |acc, b| {
+            Some((acc.0 + b.full?, acc.1 + b.full_design?))
+        })?;
+        (design > 0.0).then(|| full / design * 100.0)
+    }
+
+    /// Highest cycle count of all batteries.
+    fn cycles(&self) -> Option<u32> {
+        self.state.batteries.iter().filter_map(|b| b.cycles).max()
+    }
+}
+
 impl Component for Battery {
     fn new_from_value(value: &serde_yml::Value) -> anyhow::Result<Self>
     where
@@ -81,13 +162,46 @@ impl Component for Battery {
     }
 
     fn update_state(&mut self) -> anyhow::Result<()> {
-        let path = &self.settings.path;
-        let battery_info =
-            BatteryInfo::new(path).context("failed to create new BatteryInfo instance")?;
+        let paths: Vec<PathBuf> = self.resolve_paths();
+        if paths != self.state.paths {
+            match paths.as_slice() {
+                [] => log::warn!("no batteries found"),
+                _ => log::info!("using batteries: {:?}", paths),
+            }
+            self.state.paths = paths;
+        }
 
-        self.state.percent = Some(battery_info.percentage.round() as i32);
-        self.state.time = Some(battery_info.time_remaining);
-        self.state.charging_state = Some(battery_info.state);
+        // skip batteries that can't be read, warning once for each
+        let mut readings: Vec<BatteryReading> = Vec::new();
+        let mut unreadable: Vec<PathBuf> = Vec::new();
+        for path in &self.state.paths {
+            match read_battery(path) {
+                Ok(reading) => readings.push(reading),
+                Err(e) => {
+                    if !self.state.unreadable.contains(path) {
+                        log::warn!("failed to read battery {}: {:#}", path.display(), e);
+                    }
+                    unreadable.push(path.clone());
+                }
+            }
+        }
+        self.state.unreadable = unreadable;
+
+        match readings.as_slice() {
+            // no batteries (that can be read), show the N/A text
+            [] => {
+                self.state.percent = None;
+                self.state.time = None;
+                self.state.charging_state = None;
+            }
+            _ => {
+                let (percent, time, charging_state) = aggregate(&readings);
+                self.state.percent = Some(percent.round() as i32);
+                self.state.time = Some(time);
+                self.state.charging_state = Some(charging_state);
+            }
+        }
+        self.state.batteries = readings;
 
         self.state.last_updated = Some(time::Instant::now());
 
@@ -96,7 +210,10 @@ impl Component for Battery {
 
     fn get_strfmt_template(&self) -> anyhow::Result<Option<&str>> {
         let format_settings = &self.settings.format;
-        let charging_state = self.state.charging_state.context("no charging state")?;
+        let Some(charging_state) = self.state.charging_state else {
+            // no batteries found, or none could be read
+            return Ok(None);
+        };
 
         let template: Option<&str> = match charging_state {
             ChargingState::Full => Some(&format_settings.full),
@@ -119,25 +236,55 @@ impl Component for Battery {
     }
 
     fn apply_strfmt_template(&self, template: &str) -> anyhow::Result<Option<String>> {
+        // per-battery variables, e.g. {p0}, {t1}
+        let per_battery = self.state.batteries.iter().enumerate().flat_map(|(i, b)| {
+            [
+                (format!("p{}", i), (b.percent.round() as i32).to_string()),
+                (format!("t{}", i), format_duration_trimmed(b.time)),
+            ]
+        });
+
         apply_strfmt!(
             template,
+            extra: per_battery,
             "p" => match self.state.percent {
                 None => "N/A".to_string(),
                 Some(v) => v.to_string(),
             },
             "t" => match self.state.time {
                 None => "N/A".to_string(),
-                Some(t) => {
-                    let visual_time = &humantime::format_duration(t).to_string();
-                    static RE: Lazy<Regex> = Lazy::new(
-                        || Regex::new(r"\s[0-9]+s$").expect("could not build regex")
-                    );
-                    RE.replace(visual_time, "").to_string()
-                }
-            }
+                Some(t) => format_duration_trimmed(t),
+            },
+            "watts" => match self.watts() {
+                None => "N/A".to_string(),
+                Some(w) => format!("{:.1}", w),
+            },
+            "health" => match self.health() {
+                None => "N/A".to_string(),
+                Some(h) => format!("{:.0}", h),
+            },
+            "cycles" => match self.cycles() {
+                None => "N/A".to_string(),
+                Some(c) => c.to_string(),
+            },
+            "status" => match self.state.charging_state {
+                None => "N/A".to_string(),
+                Some(ChargingState::Charging) => "Charging".to_string(),
+                Some(ChargingState::Discharging) => "Discharging".to_string(),
+                Some(ChargingState::Full) => "Full".to_string(),
+                Some(ChargingState::NotCharging) => "Not charging".to_string(),
+            },
         )
     }
 
+    fn get_watch_targets(&self) -> anyhow::Result<Option<WatchTargets>> {
+        Ok(self.settings.watch.then(|| WatchTargets {
+            devices: self.resolve_paths(),
+            file: "uevent",
+            subsystem: "power_supply",
+        }))
+    }
+
     impl_component_methods!(
         set_cache,
         get_last_updated,
@@ -147,3 +294,139 @@ impl Component for Battery {
         default_output
     );
 }
+
+/// Find every power supply of type "Battery", skipping peripheral (device scope) batteries.
+fn discover_batteries() -> Vec<PathBuf> {
+    list_sysfs_class("/sys/class/power_supply", "")
+        .into_iter()
+        .filter(|p| read_sysfs_attr(&p.join("type")).as_deref() == Some("Battery"))
+        .filter(|p| read_sysfs_attr(&p.join("scope")).as_deref() != Some("Device"))
+        .collect()
+}
+
+fn read_battery(path: &Path) -> anyhow::Result<BatteryReading> {
+    let battery_info = BatteryInfo::new(path)
+        .with_context(|| format!("failed to create BatteryInfo for {}", path.display()))?;
+
+    // batteries report either energy (µWh, µW) or charge (µAh, µA)
+    let read_either = |energy: &str, charge: &str| {
+        read_sysfs_num(&path.join(energy)).or_else(|| read_sysfs_num(&path.join(charge)))
+    };
+
+    Ok(BatteryReading {
+        energy: read_sysfs_num(&path.join("energy_now")).is_some(),
+        percent: battery_info.percentage,
+        time: battery_info.time_remaining,
+        charging_state: battery_info.state,
+        now: read_either("energy_now", "charge_now"),
+        full: read_either("energy_full", "charge_full"),
+        rate: read_either("power_now", "current_now").map(f64::abs),
+        full_design: read_either("energy_full_design", "charge_full_design"),
+        watts: read_watts(path),
+        cycles: read_sysfs_num(&path.join("cycle_count")).map(|c| c as u32),
+    })
+}
+
+/// Power draw in watts, from `power_now` (µW) or `current_now` (µA) * `voltage_now` (µV).
+fn read_watts(path: &Path) -> Option<f64> {
+    if let Some(power) = read_sysfs_num(&path.join("power_now")) {
+        return Some(power.abs() / 1e6);
+    }
+    let current = read_sysfs_num(&path.join("current_now"))?;
+    let voltage = read_sysfs_num(&path.join("voltage_now"))?;
+    Some((current * voltage).abs() / 1e12)
+}
+
+/// Whether the capacities of all batteries can be added up, which µWh and µAh can't.
+fn same_unit(readings: &[BatteryReading]) -> bool {
+    readings.iter().all(|b| b.energy == readings[0].energy)
+}
+
+/// Combine several batteries into an overall percent, time remaining and charging state.
+fn aggregate(readings: &[BatteryReading]) -> (f32, time::Duration, ChargingState) {
+    if let [single] = readings {
+        return (single.percent, single.time, single.charging_state);
+    }
+
+    let states = || readings.iter().map(|b| b.charging_state);
+    let charging_state = if states().any(|s| matches!(s, ChargingState::Charging)) {
+        ChargingState::Charging
+    } else if states().any(|s| matches!(s, ChargingState::Discharging)) {
+        ChargingState::Discharging
+    } else if states().all(|s| matches!(s, ChargingState::Full)) {
+        ChargingState::Full
+    } else {
+        ChargingState::NotCharging
+    };
+
+    let sums: Option<(f64, f64, f64)> = readings
+        .iter()
+        .try_fold((0.0, 0.0, 0.0), |acc, b| {
+            Some((
+                acc.0 + b.now?,
+                acc.1 + b.full?,
+                acc.2 + b.rate.unwrap_or(0.0),
+            ))
+        })
+        .filter(|_| same_unit(readings));
+
+    match sums {
+        Some((now, full, rate)) if full > 0.0 => {
+            // weight the percentage by each battery's capacity
+            let percent = (now / full * 100.0) as f32;
+            let hours = match charging_state {
+                ChargingState::Discharging if rate > 0.0 => now / rate,
+                ChargingState::Charging if rate > 0.0 => (full - now) / rate,
+                _ => 0.0,
+            };
+            (
+                percent,
+                time::Duration::from_secs_f64(hours * 3600.0),
+                charging_state,
+            )
+        }
+        _ => {
+            // sysfs doesn't expose comparable capacities, fall back to a plain average
+            let percent = readings.iter().map(|b| b.percent).sum::<f32>() / readings.len() as f32;
+            let time = readings.iter().map(|b| b.time).sum();
+            (percent, time, charging_state)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(energy: bool, now: f64, full: f64, percent: f32) -> BatteryReading {
+        BatteryReading {
+            percent,
+            time: time::Duration::ZERO,
+            charging_state: ChargingState::Discharging,
+            energy,
+            now: Some(now),
+            full: Some(full),
+            rate: None,
+            full_design: None,
+            watts: None,
+            cycles: None,
+        }
+    }
+
+    #[test]
+    fn aggregates_by_unit() {
+        // same unit, weighted by capacity
+        let (percent, _, _) = aggregate(&[
+            reading(true, 10.0, 100.0, 10.0),
+            reading(true, 300.0, 300.0, 100.0),
+        ]);
+        assert_eq!(percent.round(), 78.0);
+
+        // energy and charge, plain average
+        let (percent, _, _) = aggregate(&[
+            reading(true, 10.0, 100.0, 10.0),
+            reading(false, 300.0, 300.0, 100.0),
+        ]);
+        assert_eq!(percent.round(), 55.0);
+    }
+}
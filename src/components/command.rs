@@ -1,9 +1,23 @@
-use std::time;
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{BufRead, BufReader, Write},
+    os::unix::process::CommandExt,
+    path::PathBuf,
+    process::{self, Stdio},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, RecvTimeoutError, Sender},
+    },
+    thread, time,
+};
 
+use anyhow::Context;
+use regex::Regex;
 use serde::Deserialize;
 use smart_default::SmartDefault;
 
-use crate::{impl_component_methods, new_from_value};
+use crate::{errors::MyErrors, impl_component_methods, signals::WAKE, utils::find_current_level};
 
 use super::Component;
 
@@ -17,10 +31,43 @@ pub struct Command {
 
 #[derive(Debug, SmartDefault)]
 pub struct CommandState {
+    /// The newest result from the command thread, taken by `update_state`.
+    pub latest: Arc<Mutex<Option<CommandResult>>>,
+    /// Asks the command thread to run the command now.
+    pub rerun: Option<Sender<()>>,
+    /// The last successful output.
+    pub output: Option<String>,
+    /// Fields parsed from `output`.
+    pub fields: HashMap<String, String>,
+    /// Compiled `pattern` setting.
+    pub regex: Option<Regex>,
+    /// What to run, built from the settings.
+    pub spec: CommandSpec,
+    /// Whether the last run failed or timed out, so `output` is out of date.
+    pub stale: bool,
+    /// Exit code of the last run.
+    pub exit_code: Option<i32>,
+    /// Stderr of the last run.
+    pub stderr: String,
+    /// Whether the last run exited with `urgent_exit_code`, selecting the
+    /// `urgent` template.
+    pub urgent: bool,
     pub last_updated: Option<time::Instant>,
     pub cache: Option<String>,
 }
 
+/// The outcome of running the command once (or of one line of a persistent command).
+#[derive(Debug)]
+pub enum CommandResult {
+    Output {
+        code: i32,
+        stdout: String,
+        stderr: String,
+    },
+    /// The command couldn't be run, was killed or timed out.
+    Failed,
+}
+
 #[derive(Debug, SmartDefault, Deserialize)]
 #[serde(default, deny_unknown_fields)]
 pub struct CommandSettings {
@@ -32,61 +79,635 @@ pub struct CommandSettings {
 
     #[default("echo -n ' hello world! '")]
     pub shell_command: String,
+
+    /// Shell to run `shell_command` with (as `<shell> -c <shell_command>`).
+    #[default("sh")]
+    pub shell: String,
+
+    /// Run this program and arguments directly instead of `shell_command`.
+    #[default(None)]
+    pub argv: Option<Vec<String>>,
+
+    /// Working directory to run the command in.
+    #[default(None)]
+    pub cwd: Option<PathBuf>,
+
+    /// Extra environment variables for the command.
+    #[default(HashMap::new())]
+    pub env: HashMap<String, String>,
+
+    /// Text to write to the command's stdin.
+    #[default(None)]
+    pub stdin: Option<String>,
+
+    /// Trim whitespace around the output.
+    #[default(false)]
+    pub trim: bool,
+
+    /// Passed to the command as $BLOCK_INSTANCE, like i3blocks.
+    #[default(None)]
+    pub instance: Option<String>,
+
+    /// Spawn the command once and use every line it prints as the output.
+    #[default(false)]
+    pub persistent: bool,
+
+    /// Kill the command if it runs longer than this many ms.
+    #[default(None)]
+    pub timeout: Option<u64>,
+
+    /// How to parse the output into template variables.
+    pub parser: CommandParser,
+
+    /// Regex with named captures, for the regex parser.
+    #[default(None)]
+    pub pattern: Option<String>,
+
+    /// Numeric field that `format.levels` is compared against.
+    #[default(None)]
+    pub level_field: Option<String>,
+
+    /// Exit code that selects the `urgent` template, like i3blocks' urgent
+    /// exit code. It isn't treated as a failure.
+    #[default(33)]
+    pub urgent_exit_code: i32,
+
+    #[default(CommandFormatSettings::default())]
+    pub format: CommandFormatSettings,
+}
+
+#[derive(Debug, SmartDefault, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandFormatSettings {
+    #[default("{output}")]
+    pub default: String,
+
+    /// The template to use when the last run failed or timed out.
+    #[default("{output}")]
+    pub stale: String,
+
+    #[default(None)]
+    pub levels: Option<Vec<(i32, String)>>,
+
+    /// Templates for specific exit codes. Unlisted non-zero exit codes keep
+    /// the last good output, using the stale template.
+    #[default(HashMap::new())]
+    pub by_exit_code: HashMap<i32, String>,
+
+    /// The template to use on `urgent_exit_code`, unless `by_exit_code` has one.
+    #[default(None)]
+    pub urgent: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandParser {
+    /// Only provide the raw output as {output}.
+    #[default]
+    None,
+    /// A JSON object, each top-level key becomes a variable.
+    Json,
+    /// One `key=value` pair per line.
+    KeyValue,
+    /// The named captures of `pattern`.
+    Regex,
 }
 
+/// A program, its arguments and the environment to run it in.
+#[derive(Debug, Default, Clone)]
+pub struct CommandSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    pub env: HashMap<String, String>,
+    pub stdin: Option<String>,
+}
+
+impl CommandSpec {
+    fn from_settings(settings: &CommandSettings) -> anyhow::Result<Self> {
+        let (program, args) = match &settings.argv {
+            Some(argv) => {
+                let (program, args) = argv.split_first().context("argv is empty")?;
+                (program.clone(), args.to_vec())
+            }
+            None => (
+                settings.shell.clone(),
+                vec!["-c".to_string(), settings.shell_command.clone()],
+            ),
+        };
+
+        // the variables i3blocks sets, so its scripts run unmodified
+        // ($BLOCK_NAME is the component's name, see `set_name`)
+        let mut env = HashMap::from([
+            (
+                "BLOCK_INSTANCE".to_string(),
+                settings.instance.clone().unwrap_or_default(),
+            ),
+            (
+                "BLOCK_INTERVAL".to_string(),
+                (settings.refresh_interval / 1000).to_string(),
+            ),
+            ("BLOCK_SIGNAL".to_string(), settings.signal.to_string()),
+        ]);
+        env.extend(settings.env.clone());
+
+        let cwd = settings.cwd.as_ref().map(|p| match p.strip_prefix("~") {
+            Ok(rest) => std::env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(rest))
+                .unwrap_or_else(|| p.clone()),
+            Err(_) => p.clone(),
+        });
+
+        Ok(Self {
+            program,
+            args,
+            cwd,
+            env,
+            stdin: settings.stdin.clone(),
+        })
+    }
+
+    /// Spawn the command in its own process group, so it can be killed along
+    /// with its children, and so it doesn't outlive ferristatus.
+    fn spawn(&self, stderr: Stdio) -> std::io::Result<process::Child> {
+        let mut cmd = process::Command::new(&self.program);
+        cmd.args(&self.args)
+            .envs(&self.env)
+            .stdin(match self.stdin {
+                Some(_) => Stdio::piped(),
+                None => Stdio::null(),
+            })
+            .stdout(Stdio::piped())
+            .stderr(stderr)
+            .process_group(0);
+        if let Some(cwd) = &self.cwd {
+            cmd.current_dir(cwd);
+        }
+        // SAFETY: the pre_exec closure only makes a single async-signal-safe prctl(2) call.
+        unsafe {
+            cmd.pre_exec(|| {
+                libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM);
+                Ok(())
+            });
+        }
+        let mut child = cmd.spawn()?;
+
+        // write stdin from another thread, so a command that doesn't read it can't block us
+        if let (Some(mut pipe), Some(input)) = (child.stdin.take(), self.stdin.clone()) {
+            thread::spawn(move || {
+                let _ = pipe.write_all(input.as_bytes());
+            });
+        }
+
+        Ok(child)
+    }
+}
+
+impl fmt::Display for CommandSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+/// Restart delays for a persistent command that keeps exiting.
+const BACKOFF_MIN: time::Duration = time::Duration::from_secs(1);
+const BACKOFF_MAX: time::Duration = time::Duration::from_secs(60);
+
 impl Component for Command {
     fn new_from_value(value: &serde_yml::Value) -> anyhow::Result<Self>
     where
         Self: std::marker::Sized,
     {
-        new_from_value!(
-            value => CommandSettings
-        )
+        let mut settings: CommandSettings = crate::deserialize_value!(value);
+        crate::utils::sort_levels(&mut settings.format.levels);
+
+        let regex = match (settings.parser, &settings.pattern) {
+            (CommandParser::Regex, Some(p)) => {
+                Some(Regex::new(p).with_context(|| format!("invalid pattern: {}", p))?)
+            }
+            (CommandParser::Regex, None) => anyhow::bail!("the regex parser needs a pattern"),
+            _ => None,
+        };
+
+        Ok(Self {
+            state: CommandState {
+                regex,
+                spec: CommandSpec::from_settings(&settings)?,
+                ..CommandState::default()
+            },
+            settings,
+        })
     }
 
     fn update_state(&mut self) -> anyhow::Result<()> {
-        anyhow::bail!("not applicable")
+        let result = self
+            .state
+            .latest
+            .lock()
+            .map_err(MyErrors::from_poison_error)?
+            .take();
+
+        match result {
+            Some(CommandResult::Output {
+                code,
+                stdout,
+                stderr,
+            }) => {
+                let stdout = match self.settings.trim {
+                    true => stdout.trim().to_string(),
+                    false => stdout,
+                };
+                let urgent = code == self.settings.urgent_exit_code;
+                let expected =
+                    code == 0 || urgent || self.settings.format.by_exit_code.contains_key(&code);
+
+                self.state.exit_code = Some(code);
+                self.state.stderr = stderr.trim().to_string();
+                self.state.urgent = urgent;
+
+                if !expected {
+                    log::error!(
+                        "command exited with {}: {}: {}",
+                        code,
+                        self.state.spec,
+                        self.state.stderr
+                    );
+                    self.state.stale = true;
+                } else {
+                    let fields = match self.parse(&stdout) {
+                        Ok(fields) => Some(fields),
+                        // a script reporting its state through the exit code
+                        // may print nothing parseable, which isn't a failure
+                        Err(_) if code != 0 => Some(HashMap::new()),
+                        Err(e) => {
+                            log::error!("failed to parse command output: {:#}", e);
+                            None
+                        }
+                    };
+                    match fields {
+                        Some(fields) => {
+                            self.state.output = Some(stdout);
+                            self.state.fields = fields;
+                            self.state.stale = false;
+                        }
+                        None => self.state.stale = true,
+                    }
+                }
+            }
+            Some(CommandResult::Failed) => {
+                self.state.exit_code = None;
+                self.state.urgent = false;
+                self.state.stale = true;
+            }
+            // nothing new, so this update was asked for (e.g. by a signal)
+            None => {
+                if let Some(rerun) = &self.state.rerun {
+                    let _ = rerun.send(());
+                }
+            }
+        }
+
+        self.state.last_updated = Some(time::Instant::now());
+
+        Ok(())
     }
 
     fn get_strfmt_template(&self) -> anyhow::Result<Option<&str>> {
-        anyhow::bail!("not applicable")
+        let format = &self.settings.format;
+        if self.state.output.is_none() {
+            // nothing to show until the command has succeeded once
+            return Ok(None);
+        }
+        if self.state.stale {
+            return Ok(Some(&format.stale));
+        }
+        if let Some(t) = self
+            .state
+            .exit_code
+            .and_then(|c| format.by_exit_code.get(&c))
+        {
+            return Ok(Some(t));
+        }
+        if let (true, Some(t)) = (self.state.urgent, &format.urgent) {
+            return Ok(Some(t));
+        }
+
+        let value: Option<i32> = self
+            .settings
+            .level_field
+            .as_ref()
+            .and_then(|f| self.state.fields.get(f))
+            .and_then(|v| v.trim().parse::<f64>().ok())
+            .map(|v| v.round() as i32);
+
+        let template: &str = match (value, &format.levels) {
+            (Some(v), Some(lvls)) => find_current_level(lvls, &v)?,
+            _ => &format.default,
+        };
+
+        Ok(Some(template))
     }
 
-    fn apply_strfmt_template(&self, _template: &str) -> anyhow::Result<Option<String>> {
-        anyhow::bail!("not applicable")
+    fn apply_strfmt_template(&self, template: &str) -> anyhow::Result<Option<String>> {
+        let output = self.state.output.as_deref().unwrap_or_default();
+        // fields depend on what the command printed, so missing ones aren't an error
+        let formatted = strfmt::strfmt_map(template, |mut fmt: strfmt::Formatter| {
+            let exit_code = self.state.exit_code.map(|c| c.to_string());
+            let value = match fmt.key {
+                "output" => output,
+                "stderr" => &self.state.stderr,
+                "exit_code" => exit_code.as_deref().unwrap_or("N/A"),
+                key => self.state.fields.get(key).map_or("N/A", String::as_str),
+            };
+            fmt.str(value)
+        })?;
+
+        Ok(Some(formatted))
+    }
+
+    fn default_output(&self) -> anyhow::Result<&str> {
+        Ok("")
     }
 
-    fn set_cache(&mut self, _str: String) -> anyhow::Result<()> {
-        anyhow::bail!("not applicable")
+    fn next_update(&self) -> anyhow::Result<Option<time::Instant>> {
+        // after the first update, only when the command thread has a new result
+        let pending = self
+            .state
+            .latest
+            .lock()
+            .map_err(MyErrors::from_poison_error)?
+            .is_some();
+        match (self.state.last_updated, pending) {
+            (None, _) | (_, true) => Ok(Some(time::Instant::now())),
+            (Some(_), false) => Ok(None),
+        }
+    }
+
+    fn set_name(&mut self, name: &str) -> anyhow::Result<()> {
+        // unless the env setting overrides it
+        self.state
+            .spec
+            .env
+            .entry("BLOCK_NAME".to_string())
+            .or_insert_with(|| name.to_string());
+        Ok(())
     }
 
-    fn update(&mut self) -> anyhow::Result<()> {
-        // run shell command
-        let output = std::process::Command::new("sh")
-            .arg("-c")
-            .arg(&self.settings.shell_command)
-            .output()?;
+    fn spawn_background(&mut self, sender: Sender<u32>) -> anyhow::Result<()> {
+        let spec = self.state.spec.clone();
+        let latest = Arc::clone(&self.state.latest);
 
-        if output.status.success() {
-            // stdout to String
-            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-            // Update cache
-            self.state.cache = Some(stdout);
+        let builder = thread::Builder::new().name("command".into());
+        if self.settings.persistent {
+            builder.spawn(move || run_persistent(&spec, &latest, &sender))
         } else {
-            // stderr to String
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            // return io error
-            Err(std::io::Error::new(std::io::ErrorKind::Other, stderr))?;
+            let (rerun_tx, rerun_rx) = mpsc::channel();
+            self.state.rerun = Some(rerun_tx);
+
+            let interval = time::Duration::from_millis(self.settings.refresh_interval);
+            let timeout = self.settings.timeout.map(time::Duration::from_millis);
+            builder.spawn(move || {
+                loop {
+                    // run when asked to, or every refresh_interval
+                    if let Err(RecvTimeoutError::Disconnected) = rerun_rx.recv_timeout(interval) {
+                        return;
+                    }
+
+                    let result = run_once(&spec, timeout);
+                    // coalesce requests that came in while the command was running
+                    while rerun_rx.try_recv().is_ok() {}
+
+                    match latest.lock() {
+                        Ok(mut l) => *l = Some(result),
+                        Err(_) => return,
+                    }
+                    if sender.send(WAKE).is_err() {
+                        return;
+                    }
+                }
+            })
         }
+        .context("failed to spawn command thread")?;
 
         Ok(())
     }
 
     impl_component_methods!(
+        set_cache,
         get_last_updated,
         get_refresh_interval,
         get_signal_value,
-        get_cache,
-        default_output
+        get_cache
     );
 }
+
+impl Command {
+    /// Parse the output into template variables, according to `parser`.
+    fn parse(&self, output: &str) -> anyhow::Result<HashMap<String, String>> {
+        match self.settings.parser {
+            CommandParser::None => Ok(HashMap::new()),
+            CommandParser::Json => parse_json(output),
+            CommandParser::KeyValue => Ok(parse_key_value(output)),
+            CommandParser::Regex => {
+                let regex = self.state.regex.as_ref().context("no pattern")?;
+                let captures = regex
+                    .captures(output)
+                    .context("pattern doesn't match the output")?;
+                Ok(regex
+                    .capture_names()
+                    .flatten()
+                    .filter_map(|name| {
+                        Some((name.to_string(), captures.name(name)?.as_str().to_string()))
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+/// Each top-level key of a JSON object, with nested values kept as (single-line)
+/// JSON text.
+fn parse_json(output: &str) -> anyhow::Result<HashMap<String, String>> {
+    let value: serde_json::Value = serde_json::from_str(output).context("invalid JSON")?;
+    let object = value.as_object().context("expected a JSON object")?;
+
+    object
+        .iter()
+        .map(|(k, v)| {
+            let value = match v {
+                serde_json::Value::String(s) => s.clone(),
+                serde_json::Value::Null => String::new(),
+                other => serde_json::to_string(other)?,
+            };
+            Ok((k.clone(), value))
+        })
+        .collect()
+}
+
+/// Every `key=value` line, trimmed. Other lines are ignored.
+fn parse_key_value(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect()
+}
+
+/// Run the command to completion, killing its process group after `timeout`.
+fn run_once(spec: &CommandSpec, timeout: Option<time::Duration>) -> CommandResult {
+    let child = match spec.spawn(Stdio::piped()) {
+        Ok(child) => child,
+        Err(e) => {
+            log::error!("failed to spawn command: {}: {}", spec, e);
+            return CommandResult::Failed;
+        }
+    };
+    let pgid = child.id() as libc::pid_t;
+
+    // wait in another thread, so a hung command can be timed out
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(child.wait_with_output());
+    });
+
+    let output = match timeout {
+        Some(t) => match rx.recv_timeout(t) {
+            Ok(output) => output,
+            Err(_) => {
+                log::warn!("command timed out after {:?}, killing it: {}", t, spec);
+                // SAFETY: plain kill(2) call on the process group we created.
+                unsafe { libc::kill(-pgid, libc::SIGKILL) };
+                return CommandResult::Failed;
+            }
+        },
+        None => match rx.recv() {
+            Ok(output) => output,
+            Err(_) => return CommandResult::Failed,
+        },
+    };
+
+    match output {
+        Ok(output) => match output.status.code() {
+            Some(code) => CommandResult::Output {
+                code,
+                stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            },
+            None => {
+                log::error!("command was killed ({}): {}", output.status, spec);
+                CommandResult::Failed
+            }
+        },
+        Err(e) => {
+            log::error!("failed to wait for command: {}: {}", spec, e);
+            CommandResult::Failed
+        }
+    }
+}
+
+/// Keep the command running, storing each line it prints in `latest` and waking
+/// the main loop so the bar re-renders. Restarts the command with an increasing delay
+/// whenever it exits, until the main loop goes away.
+fn run_persistent(spec: &CommandSpec, latest: &Mutex<Option<CommandResult>>, sender: &Sender<u32>) {
+    let mut backoff = BACKOFF_MIN;
+
+    loop {
+        let started = time::Instant::now();
+
+        match spec.spawn(Stdio::inherit()) {
+            Ok(mut child) => {
+                if let Some(stdout) = child.stdout.take() {
+                    for line in BufReader::new(stdout).lines() {
+                        let Ok(line) = line else {
+                            break;
+                        };
+                        match latest.lock() {
+                            Ok(mut l) => {
+                                *l = Some(CommandResult::Output {
+                                    code: 0,
+                                    stdout: line,
+                                    stderr: String::new(),
+                                })
+                            }
+                            Err(_) => return,
+                        }
+                        if sender.send(WAKE).is_err() {
+                            let _ = child.kill();
+                            return;
+                        }
+                    }
+                }
+                match child.wait() {
+                    Ok(status) => log::warn!("persistent command exited ({}): {}", status, spec),
+                    Err(e) => log::error!("failed to wait for persistent command: {}", e),
+                }
+            }
+            Err(e) => log::error!("failed to spawn persistent command: {}", e),
+        }
+
+        // a command that ran for a while gets restarted quickly again
+        if started.elapsed() > BACKOFF_MAX {
+            backoff = BACKOFF_MIN;
+        }
+        log::info!("restarting persistent command in {:?}", backoff);
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(BACKOFF_MAX);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_output() {
+        let json = parse_json(r#"{"temp": 71, "state": "ok", "fan": null}"#).unwrap();
+        assert_eq!(json["temp"], "71");
+        assert_eq!(json["state"], "ok");
+        assert_eq!(json["fan"], "");
+        assert!(parse_json("[1, 2]").is_err());
+
+        let nested = parse_json(r#"{"b": [1, 2], "c": {"d": 1}}"#).unwrap();
+        assert_eq!(nested["b"], "[1,2]");
+        assert_eq!(nested["c"], r#"{"d":1}"#);
+
+        let kv = parse_key_value("a=1\n b = two \njunk\nc=x=y");
+        assert_eq!(kv["a"], "1");
+        assert_eq!(kv["b"], "two");
+        assert_eq!(kv["c"], "x=y");
+        assert_eq!(kv.len(), 3);
+    }
+
+    #[test]
+    fn expected_exit_codes_skip_parse_errors() -> anyhow::Result<()> {
+        let mut c = Command::new_from_value(&serde_yml::from_str(
+            "{parser: json, format: {by_exit_code: {1: ' off '}, urgent: ' !! '}}",
+        )?)?;
+        let run = |c: &mut Command, code: i32, stdout: &str| -> anyhow::Result<()> {
+            *c.state.latest.lock().unwrap() = Some(CommandResult::Output {
+                code,
+                stdout: stdout.to_string(),
+                stderr: String::new(),
+            });
+            c.update_state()
+        };
+
+        run(&mut c, 1, "")?;
+        assert!(!c.state.stale);
+        assert!(c.state.fields.is_empty());
+        assert_eq!(c.get_strfmt_template()?, Some(" off "));
+
+        run(&mut c, 33, "not json")?;
+        assert!(!c.state.stale);
+        assert_eq!(c.get_strfmt_template()?, Some(" !! "));
+
+        // a successful run has to print what the parser expects
+        run(&mut c, 0, "not json")?;
+        assert!(c.state.stale);
+        Ok(())
+    }
+}
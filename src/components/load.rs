@@ -0,0 +1,140 @@
+use std::{fs, thread, time};
+
+use anyhow::Context;
+use serde::Deserialize;
+use serde_yml::Value;
+use smart_default::SmartDefault;
+
+use crate::{apply_strfmt, impl_component_methods, new_from_value, utils::find_current_level};
+
+use super::Component;
+
+// Load ///////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, SmartDefault)]
+pub struct Load {
+    pub state: LoadState,
+    pub settings: LoadSettings,
+}
+
+#[derive(Debug, SmartDefault)]
+pub struct LoadState {
+    /// 1, 5 and 15 minute load averages.
+    pub averages: Option<[f32; 3]>,
+    pub running: Option<u32>,
+    pub total: Option<u32>,
+    pub last_updated: Option<time::Instant>,
+    pub cache: Option<String>,
+}
+
+#[derive(Debug, SmartDefault, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoadSettings {
+    #[default(2000)]
+    pub refresh_interval: u64,
+
+    #[default(11)]
+    pub signal: u32,
+
+    #[default(LoadFormatSettings::default())]
+    pub format: LoadFormatSettings,
+}
+
+#[derive(Debug, SmartDefault, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoadFormatSettings {
+    #[default(" load: {1m} ")]
+    pub default: String,
+
+    /// Thresholds are the 1m load as a percent of the CPU count.
+    #[default(None)]
+    pub levels: Option<Vec<(i32, String)>>,
+}
+
+impl Load {
+    /// The 1 minute load average as a percent of the number of CPUs.
+    fn percent_of_cpus(&self) -> Option<i32> {
+        let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+        self.state
+            .averages
+            .map(|[one, _, _]| (one * 100.0 / cpus as f32).round() as i32)
+    }
+}
+
+impl Component for Load {
+    fn new_from_value(value: &Value) -> anyhow::Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        new_from_value!(
+            value => LoadSettings,
+            sort_levels: true
+        )
+    }
+
+    fn update_state(&mut self) -> anyhow::Result<()> {
+        // e.g. "0.52 0.58 0.59 2/1234 5678"
+        let contents =
+            fs::read_to_string("/proc/loadavg").context("failed to read /proc/loadavg")?;
+        let fields: Vec<&str> = contents.split_whitespace().collect();
+        let [one, five, fifteen, tasks, ..] = fields.as_slice() else {
+            anyhow::bail!("unexpected format in /proc/loadavg: {}", contents);
+        };
+        let (running, total) = tasks
+            .split_once('/')
+            .context("unexpected task count format in /proc/loadavg")?;
+
+        self.state.averages = Some([one.parse()?, five.parse()?, fifteen.parse()?]);
+        self.state.running = Some(running.parse()?);
+        self.state.total = Some(total.parse()?);
+        self.state.last_updated = Some(time::Instant::now());
+
+        Ok(())
+    }
+
+    fn get_strfmt_template(&self) -> anyhow::Result<Option<&str>> {
+        let percent = &self.percent_of_cpus();
+        let levels = &self.settings.format.levels;
+
+        let template: Option<&str> = match (percent, levels) {
+            // percent is None
+            (None, _) => None,
+            // levels is None, use default formatter
+            (Some(_), None) => Some(&self.settings.format.default),
+            // levels is Some
+            (Some(perc), Some(lvls)) => Some(find_current_level(lvls, perc)?),
+        };
+
+        Ok(template)
+    }
+
+    fn apply_strfmt_template(&self, template: &str) -> anyhow::Result<Option<String>> {
+        let average = |i: usize| match self.state.averages {
+            Some(avgs) => format!("{:.2}", avgs[i]),
+            None => "N/A".to_string(),
+        };
+        apply_strfmt!(
+            template,
+            "1m" => average(0),
+            "5m" => average(1),
+            "15m" => average(2),
+            "running" => match self.state.running {
+                Some(v) => v.to_string(),
+                None => "N/A".to_string(),
+            },
+            "total" => match self.state.total {
+                Some(v) => v.to_string(),
+                None => "N/A".to_string(),
+            },
+        )
+    }
+
+    impl_component_methods!(
+        set_cache,
+        get_last_updated,
+        get_refresh_interval,
+        get_signal_value,
+        get_cache,
+        default_output
+    );
+}
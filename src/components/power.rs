@@ -0,0 +1,123 @@
+use std::{path::Path, time};
+
+use serde::Deserialize;
+use serde_yml::Value;
+use smart_default::SmartDefault;
+
+use crate::{
+    apply_strfmt, impl_component_methods, new_from_value,
+    utils::{list_sysfs_class, read_sysfs_attr},
+};
+
+use super::Component;
+
+// Power //////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, SmartDefault)]
+pub struct Power {
+    pub state: PowerState,
+    pub settings: PowerSettings,
+}
+
+#[derive(Debug, SmartDefault)]
+pub struct PowerState {
+    /// Whether any AC (Mains or USB) power supply is online.
+    pub on_ac: Option<bool>,
+    /// Name of the online power supply, e.g. "AC" or "ucsi-source-psy-USBC000:001".
+    pub adapter: Option<String>,
+    pub profile: Option<String>,
+    pub governor: Option<String>,
+    pub last_updated: Option<time::Instant>,
+    pub cache: Option<String>,
+}
+
+#[derive(Debug, SmartDefault, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PowerSettings {
+    #[default(2000)]
+    pub refresh_interval: u64,
+
+    #[default(13)]
+    pub signal: u32,
+
+    #[default(PowerFormatSettings::default())]
+    pub format: PowerFormatSettings,
+}
+
+#[derive(Debug, SmartDefault, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PowerFormatSettings {
+    #[default(" AC ")]
+    pub ac: String,
+
+    #[default(" BAT ")]
+    pub battery: String,
+}
+
+impl Component for Power {
+    fn new_from_value(value: &Value) -> anyhow::Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        new_from_value!(
+            value => PowerSettings
+        )
+    }
+
+    fn update_state(&mut self) -> anyhow::Result<()> {
+        let adapters: Vec<_> = list_sysfs_class("/sys/class/power_supply", "")
+            .into_iter()
+            .filter(|p| {
+                matches!(
+                    read_sysfs_attr(&p.join("type")).as_deref(),
+                    Some("Mains") | Some("USB")
+                )
+            })
+            .collect();
+
+        let online = adapters
+            .iter()
+            .find(|p| read_sysfs_attr(&p.join("online")).as_deref() == Some("1"));
+
+        self.state.on_ac = (!adapters.is_empty()).then_some(online.is_some());
+        self.state.adapter = online
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string());
+        self.state.profile = read_sysfs_attr(Path::new("/sys/firmware/acpi/platform_profile"));
+        self.state.governor = read_sysfs_attr(Path::new(
+            "/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor",
+        ));
+        self.state.last_updated = Some(time::Instant::now());
+
+        Ok(())
+    }
+
+    fn get_strfmt_template(&self) -> anyhow::Result<Option<&str>> {
+        let template: Option<&str> = match self.state.on_ac {
+            // no AC adapters found
+            None => None,
+            Some(true) => Some(&self.settings.format.ac),
+            Some(false) => Some(&self.settings.format.battery),
+        };
+
+        Ok(template)
+    }
+
+    fn apply_strfmt_template(&self, template: &str) -> anyhow::Result<Option<String>> {
+        apply_strfmt!(
+            template,
+            "adapter" => self.state.adapter.clone().unwrap_or("N/A".to_string()),
+            "profile" => self.state.profile.clone().unwrap_or("N/A".to_string()),
+            "governor" => self.state.governor.clone().unwrap_or("N/A".to_string()),
+        )
+    }
+
+    impl_component_methods!(
+        set_cache,
+        get_last_updated,
+        get_refresh_interval,
+        get_signal_value,
+        get_cache,
+        default_output
+    );
+}
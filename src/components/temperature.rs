@@ -0,0 +1,229 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time,
+};
+
+use serde::Deserialize;
+use serde_yml::Value;
+use smart_default::SmartDefault;
+
+use crate::{
+    apply_strfmt, impl_component_methods, new_from_value,
+    utils::{find_current_level, list_sysfs_class, read_sysfs_attr},
+};
+
+use super::Component;
+
+// Temperature ////////////////////////////////////////////////////////////////
+
+#[derive(Debug, SmartDefault)]
+pub struct Temperature {
+    pub state: TemperatureState,
+    pub settings: TemperatureSettings,
+}
+
+#[derive(Debug, SmartDefault)]
+pub struct TemperatureState {
+    /// Average of all matching sensors, in degrees Celsius.
+    pub celsius: Option<f32>,
+    /// Hottest of all matching sensors, in degrees Celsius.
+    pub max: Option<f32>,
+    /// The matching sensors, as of the last update.
+    pub sensors: Option<Vec<PathBuf>>,
+    pub last_updated: Option<time::Instant>,
+    pub cache: Option<String>,
+}
+
+#[derive(Debug, SmartDefault, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemperatureSettings {
+    #[default(2000)]
+    pub refresh_interval: u64,
+
+    #[default(10)]
+    pub signal: u32,
+
+    /// Only use thermal zones whose `type` matches (e.g. "x86_pkg_temp").
+    #[default(None)]
+    pub zone_type: Option<String>,
+
+    /// Only use hwmon sensors whose `name` matches (e.g. "coretemp").
+    #[default(None)]
+    pub hwmon: Option<String>,
+
+    /// Only use hwmon sensors whose `temp*_label` matches (e.g. "Package id 0").
+    #[default(None)]
+    pub label: Option<String>,
+
+    /// Temperature (°C) at or above which the critical template is used.
+    #[default(90)]
+    pub critical: i32,
+
+    #[default(TemperatureFormatSettings::default())]
+    pub format: TemperatureFormatSettings,
+}
+
+#[derive(Debug, SmartDefault, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemperatureFormatSettings {
+    #[default(" {c}°C ")]
+    pub default: String,
+
+    #[default(" HOT({max}°C) ")]
+    pub critical: String,
+
+    #[default(None)]
+    pub levels: Option<Vec<(i32, String)>>,
+}
+
+impl Component for Temperature {
+    fn new_from_value(value: &Value) -> anyhow::Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        new_from_value!(
+            value => TemperatureSettings,
+            sort_levels: true
+        )
+    }
+
+    fn update_state(&mut self) -> anyhow::Result<()> {
+        let sensors: Vec<PathBuf> = find_sensors(&self.settings);
+        if self.state.sensors.as_ref() != Some(&sensors) {
+            match sensors.as_slice() {
+                [] => log::warn!("no matching temperature sensors found"),
+                _ => log::info!("using temperature sensors: {:?}", sensors),
+            }
+        }
+
+        let readings: Vec<f32> = sensors
+            .iter()
+            .filter_map(|p| read_millidegrees(p))
+            .collect();
+        self.state.sensors = Some(sensors);
+
+        if readings.is_empty() {
+            self.state.celsius = None;
+            self.state.max = None;
+        } else {
+            let sum: f32 = readings.iter().sum();
+            self.state.celsius = Some(sum / readings.len() as f32);
+            self.state.max = readings.iter().copied().reduce(f32::max);
+        }
+
+        self.state.last_updated = Some(time::Instant::now());
+
+        Ok(())
+    }
+
+    fn get_strfmt_template(&self) -> anyhow::Result<Option<&str>> {
+        let max = &self.state.max.map(|v| v.round() as i32);
+        let levels = &self.settings.format.levels;
+
+        let template: Option<&str> = match (max, levels) {
+            // no readings
+            (None, _) => None,
+            // at or above the critical threshold
+            (Some(m), _) if *m >= self.settings.critical => Some(&self.settings.format.critical),
+            // levels is None, use default formatter
+            (Some(_), None) => Some(&self.settings.format.default),
+            // levels is Some
+            (Some(m), Some(lvls)) => Some(find_current_level(lvls, m)?),
+        };
+
+        Ok(template)
+    }
+
+    fn apply_strfmt_template(&self, template: &str) -> anyhow::Result<Option<String>> {
+        apply_strfmt!(
+            template,
+            "c" => match self.state.celsius {
+                Some(v) => (v.round() as i32).to_string(),
+                None => "N/A".to_string(),
+            },
+            "f" => match self.state.celsius {
+                Some(v) => ((v * 9.0 / 5.0 + 32.0).round() as i32).to_string(),
+                None => "N/A".to_string(),
+            },
+            "max" => match self.state.max {
+                Some(v) => (v.round() as i32).to_string(),
+                None => "N/A".to_string(),
+            },
+        )
+    }
+
+    impl_component_methods!(
+        set_cache,
+        get_last_updated,
+        get_refresh_interval,
+        get_signal_value,
+        get_cache,
+        default_output
+    );
+}
+
+/// Read a sysfs temperature file (millidegrees Celsius) as degrees Celsius.
+fn read_millidegrees(path: &Path) -> Option<f32> {
+    let raw: f32 = fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    Some(raw / 1000.0)
+}
+
+/// Whether the attribute at `path` equals `expected` (always true if unset).
+fn attr_matches(path: &Path, expected: &Option<String>) -> bool {
+    match expected {
+        Some(e) => read_sysfs_attr(path).as_deref() == Some(e.as_str()),
+        None => true,
+    }
+}
+
+/// Collect the paths of every temperature input matching the settings.
+/// With no selectors set, every thermal zone is used.
+fn find_sensors(settings: &TemperatureSettings) -> Vec<PathBuf> {
+    let use_hwmon = settings.hwmon.is_some() || settings.label.is_some();
+    let use_thermal = settings.zone_type.is_some() || !use_hwmon;
+
+    let mut sensors = Vec::new();
+
+    if use_thermal {
+        for zone in list_sysfs_class("/sys/class/thermal", "thermal_zone") {
+            if attr_matches(&zone.join("type"), &settings.zone_type) {
+                sensors.push(zone.join("temp"));
+            }
+        }
+    }
+
+    if use_hwmon {
+        for hwmon in list_sysfs_class("/sys/class/hwmon", "hwmon") {
+            if !attr_matches(&hwmon.join("name"), &settings.hwmon) {
+                continue;
+            }
+            let Ok(dir) = fs::read_dir(&hwmon) else {
+                continue;
+            };
+            let mut inputs: Vec<PathBuf> = dir
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.file_name()
+                        .map(|n| n.to_string_lossy())
+                        .is_some_and(|n| n.starts_with("temp") && n.ends_with("_input"))
+                })
+                .collect();
+            inputs.sort();
+            for input in inputs {
+                let label_path = input.with_file_name(
+                    input
+                        .file_name()
+                        .map(|n| n.to_string_lossy().replace("_input", "_label"))
+                        .unwrap_or_default(),
+                );
+                if attr_matches(&label_path, &settings.label) {
+                    sensors.push(input);
+                }
+            }
+        }
+    }
+
+    sensors
+}
@@ -60,6 +60,10 @@ impl Component for Text {
         anyhow::bail!("not applicable")
     }
 
+    fn next_update(&self) -> anyhow::Result<Option<std::time::Instant>> {
+        Ok(None)
+    }
+
     fn update_check(&self) -> anyhow::Result<bool> {
         anyhow::bail!("not applicable")
     }
@@ -67,4 +71,9 @@ impl Component for Text {
     fn update_maybe(&mut self) -> anyhow::Result<bool> {
         Ok(false)
     }
+
+    fn set_text(&mut self, text: &str) -> anyhow::Result<bool> {
+        self.0 = text.to_string();
+        Ok(true)
+    }
 }
@@ -1,10 +1,11 @@
-use std::time;
+use std::{collections::HashMap, time};
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, Locale, TimeZone};
+use chrono_tz::Tz;
 use serde::Deserialize;
 use smart_default::SmartDefault;
 
-use crate::{apply_strfmt, impl_component_methods, new_from_value};
+use crate::{apply_strfmt, impl_component_methods, watch::ClockGranularity};
 
 use super::Component;
 
@@ -19,6 +20,10 @@ pub struct Time {
 #[derive(Debug, SmartDefault)]
 pub struct TimeState {
     pub now: Option<DateTime<Local>>,
+    /// Parsed `zones` setting.
+    pub zones: Vec<(String, Tz)>,
+    /// Parsed `locale` setting.
+    pub locale: Locale,
     pub last_updated: Option<time::Instant>,
     pub cache: Option<String>,
 }
@@ -35,6 +40,19 @@ pub struct TimeSettings {
     #[default("%a %d %b %I:%M %P".to_string())]
     pub time: String,
 
+    /// Update exactly when the formatted time changes (e.g. on the minute),
+    /// instead of every `refresh_interval`.
+    #[default(true)]
+    pub align: bool,
+
+    /// Extra time zones, as template variable name => IANA zone name.
+    #[default(HashMap::new())]
+    pub zones: HashMap<String, String>,
+
+    /// Locale for weekday and month names, e.g. "de_DE".
+    #[default(None)]
+    pub locale: Option<String>,
+
     #[default(TimeFormatSettings::default())]
     pub format: TimeFormatSettings,
 }
@@ -46,18 +64,54 @@ pub struct TimeFormatSettings {
     pub default: String,
 }
 
+impl Time {
+    fn format_datetime<T: TimeZone>(&self, datetime: &DateTime<T>) -> String
+    where
+        T::Offset: std::fmt::Display,
+    {
+        datetime
+            .format_localized(&self.settings.time, self.state.locale)
+            .to_string()
+    }
+}
+
 impl Component for Time {
     fn new_from_value(value: &serde_yml::Value) -> anyhow::Result<Self>
     where
         Self: std::marker::Sized,
     {
-        new_from_value!(
-            value => TimeSettings
-        )
+        let settings: TimeSettings = crate::deserialize_value!(value);
+
+        let zones = settings
+            .zones
+            .iter()
+            .map(|(name, zone)| -> anyhow::Result<(String, Tz)> {
+                let tz: Tz = zone
+                    .parse()
+                    .map_err(|e| anyhow::anyhow!("unknown time zone {}: {}", zone, e))?;
+                Ok((name.clone(), tz))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let locale = match &settings.locale {
+            Some(l) => Locale::try_from(l.as_str())
+                .map_err(|_| anyhow::anyhow!("unknown locale: {}", l))?,
+            None => Locale::default(),
+        };
+
+        Ok(Self {
+            settings,
+            state: TimeState {
+                zones,
+                locale,
+                ..TimeState::default()
+            },
+        })
     }
 
     fn update_state(&mut self) -> anyhow::Result<()> {
         self.state.now = Some(Local::now());
+        self.state.last_updated = Some(time::Instant::now());
         Ok(())
     }
 
@@ -66,15 +120,47 @@ impl Component for Time {
     }
 
     fn apply_strfmt_template(&self, template: &str) -> anyhow::Result<Option<String>> {
+        // one variable per extra time zone, e.g. {sf}, {ber}
+        let zones = self.state.zones.iter().map(|(name, tz)| {
+            let value = match self.state.now {
+                Some(datetime) => self.format_datetime(&datetime.with_timezone(tz)),
+                None => "N/A".to_string(),
+            };
+            (name.clone(), value)
+        });
+
         apply_strfmt!(
             template,
+            extra: zones,
             "t" => match self.state.now {
-                Some(datetime) => datetime.format(&self.settings.time).to_string(),
+                Some(datetime) => self.format_datetime(&datetime),
                 None => "N/A".to_string(),
             }
         )
     }
 
+    fn next_update(&self) -> anyhow::Result<Option<time::Instant>> {
+        match (self.settings.align, self.state.last_updated) {
+            // woken up by the clock thread instead
+            (true, Some(_)) => Ok(None),
+            (true, None) => Ok(Some(time::Instant::now())),
+            (false, last_updated) => Ok(Some(match last_updated {
+                Some(t) => t + time::Duration::from_millis(self.settings.refresh_interval),
+                None => time::Instant::now(),
+            })),
+        }
+    }
+
+    fn get_clock_granularity(&self) -> anyhow::Result<Option<ClockGranularity>> {
+        let mut granularity = format_granularity(&self.settings.time);
+        if !self.state.zones.is_empty() {
+            // other zones can be offset by 30 or 45 minutes, and change days at
+            // their own midnight
+            granularity = granularity.min(ClockGranularity::Minute);
+        }
+        Ok(self.settings.align.then_some(granularity))
+    }
+
     impl_component_methods!(
         set_cache,
         get_last_updated,
@@ -84,3 +170,48 @@ impl Component for Time {
         default_output
     );
 }
+
+/// The finest unit of time shown by a strftime format string.
+fn format_granularity(format: &str) -> ClockGranularity {
+    let mut granularity = ClockGranularity::Day;
+    let mut chars = format.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+        // skip padding flags, widths and modifiers like "%-M", "%3f" or "%:z"
+        let spec = chars
+            .by_ref()
+            .find(|c| !matches!(c, '-' | '_' | '0'..='9' | '^' | '#' | ':' | '.'));
+
+        let unit = match spec {
+            Some('S' | 's' | 'T' | 'X' | 'r' | 'c' | '+' | 'f') => ClockGranularity::Second,
+            Some('M' | 'R') => ClockGranularity::Minute,
+            Some('H' | 'I' | 'k' | 'l' | 'p' | 'P') => ClockGranularity::Hour,
+            _ => ClockGranularity::Day,
+        };
+        granularity = granularity.min(unit);
+    }
+
+    granularity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn granularity_of_formats() {
+        assert_eq!(format_granularity("%a %d %b"), ClockGranularity::Day);
+        assert_eq!(format_granularity("%a %d %b %I %P"), ClockGranularity::Hour);
+        assert_eq!(
+            format_granularity("%a %d %b %I:%M %P"),
+            ClockGranularity::Minute
+        );
+        assert_eq!(format_granularity("%-I:%M"), ClockGranularity::Minute);
+        assert_eq!(format_granularity("%H:%M:%S"), ClockGranularity::Second);
+        assert_eq!(format_granularity("%T"), ClockGranularity::Second);
+        assert_eq!(format_granularity("100%% %d"), ClockGranularity::Day);
+    }
+}
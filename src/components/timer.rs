@@ -0,0 +1,385 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    thread,
+    time::{self, Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use chrono::{Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use serde_yml::Value;
+use smart_default::SmartDefault;
+
+use crate::{
+    apply_strfmt,
+    config::default_state_dir,
+    impl_component_methods,
+    utils::{deserialize_duration, format_duration_trimmed},
+};
+
+use super::Component;
+
+// Timer //////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, SmartDefault)]
+pub struct Timer {
+    pub state: TimerState,
+    pub settings: TimerSettings,
+}
+
+#[derive(Debug, SmartDefault)]
+pub struct TimerState {
+    pub saved: SavedTimer,
+    /// Time left until expiry (countdown) or the end of the phase (pomodoro).
+    pub remaining: Option<Duration>,
+    pub elapsed: Duration,
+    pub expired: bool,
+    /// "work" or "break" in pomodoro mode.
+    pub phase: Option<&'static str>,
+    /// Current pomodoro cycle, starting at 1.
+    pub cycle: u64,
+    /// Parsed `until` setting.
+    pub until: Option<SystemTime>,
+    /// The `state_file` setting, or a file named after the component.
+    pub state_file: PathBuf,
+    pub last_updated: Option<time::Instant>,
+    pub cache: Option<String>,
+}
+
+/// The part of the state that is kept in the state file, so it survives
+/// restarts and can be changed by `ferristatus timer`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedTimer {
+    /// Unix time (ms) of the last start or resume, if running.
+    pub started_at: Option<u64>,
+    /// Time (ms) accumulated before the last start or resume.
+    pub elapsed: u64,
+    /// Number of expiries the `on_expire` command has been run for.
+    pub expiries: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimerMode {
+    #[default]
+    Countdown,
+    Stopwatch,
+    Pomodoro,
+}
+
+#[derive(Debug, SmartDefault, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimerSettings {
+    #[default(1000)]
+    pub refresh_interval: u64,
+
+    #[default(14)]
+    pub signal: u32,
+
+    pub mode: TimerMode,
+
+    /// Length of a countdown, e.g. "25m".
+    #[default(Duration::from_secs(25 * 60))]
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub duration: Duration,
+
+    /// Count down to a fixed local date and time instead, e.g. "2025-12-31 23:59".
+    #[default(None)]
+    pub until: Option<String>,
+
+    /// Length of a pomodoro work phase.
+    #[default(Duration::from_secs(25 * 60))]
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub work: Duration,
+
+    /// Length of a pomodoro break phase.
+    #[serde(rename = "break")]
+    #[default(Duration::from_secs(5 * 60))]
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub break_: Duration,
+
+    /// Shell command to run when the countdown or a pomodoro phase ends.
+    #[default(None)]
+    pub on_expire: Option<String>,
+
+    /// RT signals that run an action, e.g. `{timer_toggle: 20, timer_reset: 21}`.
+    #[default(HashMap::new())]
+    pub action_signals: HashMap<String, u32>,
+
+    /// Where to keep the timer's state, instead of a file named after the component.
+    #[default(None)]
+    pub state_file: Option<PathBuf>,
+
+    #[default(TimerFormatSettings::default())]
+    pub format: TimerFormatSettings,
+}
+
+#[derive(Debug, SmartDefault, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimerFormatSettings {
+    #[default(" {t} ")]
+    pub default: String,
+
+    #[default(" {t} (paused) ")]
+    pub paused: String,
+
+    #[default(" done ")]
+    pub expired: String,
+
+    /// The template to use before the timer is started.
+    #[default("")]
+    pub idle: String,
+}
+
+/// The actions a Timer supports.
+const ACTIONS: [&str; 4] = ["timer_start", "timer_pause", "timer_toggle", "timer_reset"];
+
+/// The current unix time in ms.
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+impl Timer {
+    fn load(&self) -> anyhow::Result<SavedTimer> {
+        match fs::read_to_string(&self.state.state_file) {
+            Ok(contents) => Ok(serde_yml::from_str(&contents).unwrap_or_else(|e| {
+                log::warn!("ignoring invalid timer state file: {}", e);
+                SavedTimer::default()
+            })),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SavedTimer::default()),
+            Err(e) => Err(e).context("failed to read timer state file"),
+        }
+    }
+
+    fn save(&self, saved: &SavedTimer) -> anyhow::Result<()> {
+        let path = &self.state.state_file;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).context("failed to create state directory")?;
+        }
+        fs::write(path, serde_yml::to_string(saved)?)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    fn run_on_expire(&self) {
+        let Some(command) = &self.settings.on_expire else {
+            return;
+        };
+        log::info!("timer expired, running: {}", command);
+        match std::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .spawn()
+        {
+            // wait in another thread, so the command is reaped without blocking the bar
+            Ok(mut child) => {
+                thread::spawn(move || {
+                    if let Err(e) = child.wait() {
+                        log::error!("failed to wait for on_expire command: {}", e);
+                    }
+                });
+            }
+            Err(e) => log::error!("failed to run on_expire command: {}", e),
+        }
+    }
+}
+
+impl Component for Timer {
+    fn new_from_value(value: &Value) -> anyhow::Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        let settings: TimerSettings = crate::deserialize_value!(value);
+        if let Some(action) = settings
+            .action_signals
+            .keys()
+            .find(|a| !ACTIONS.contains(&a.as_str()))
+        {
+            anyhow::bail!(
+                "unknown action in action_signals: {} (expected one of {})",
+                action,
+                ACTIONS.join(", ")
+            );
+        }
+
+        let until = match &settings.until {
+            Some(s) => {
+                let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+                    .iter()
+                    .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok())
+                    .with_context(|| format!("failed to parse until: {}", s))?;
+                let local = Local
+                    .from_local_datetime(&naive)
+                    .earliest()
+                    .with_context(|| format!("until is not a valid local time: {}", s))?;
+                Some(SystemTime::from(local))
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            settings,
+            state: TimerState {
+                until,
+                ..TimerState::default()
+            },
+        })
+    }
+
+    fn update_state(&mut self) -> anyhow::Result<()> {
+        let mut saved = self.load()?;
+        let now = now_ms();
+
+        let elapsed = Duration::from_millis(
+            saved.elapsed + saved.started_at.map_or(0, |s| now.saturating_sub(s)),
+        );
+
+        let (remaining, expiries, phase, cycle) = match (self.settings.mode, self.state.until) {
+            (TimerMode::Stopwatch, _) => (None, 0, None, 0),
+            (TimerMode::Countdown, Some(until)) => {
+                let remaining = until.duration_since(SystemTime::now()).unwrap_or_default();
+                (Some(remaining), remaining.is_zero() as u64, None, 0)
+            }
+            (TimerMode::Countdown, None) => {
+                let remaining = self.settings.duration.saturating_sub(elapsed);
+                (Some(remaining), remaining.is_zero() as u64, None, 0)
+            }
+            (TimerMode::Pomodoro, _) => {
+                let (work, break_) = (self.settings.work, self.settings.break_);
+                let period = (work + break_).as_millis().max(1) as u64;
+                let elapsed = elapsed.as_millis() as u64;
+                let (cycles, within) = (elapsed / period, elapsed % period);
+                let within = Duration::from_millis(within);
+                let (phase, remaining, phase_ends) = match within < work {
+                    true => ("work", work - within, 0),
+                    false => ("break", work + break_ - within, 1),
+                };
+                (
+                    Some(remaining),
+                    cycles * 2 + phase_ends,
+                    Some(phase),
+                    cycles + 1,
+                )
+            }
+        };
+
+        if expiries > saved.expiries {
+            self.run_on_expire();
+            saved.expiries = expiries;
+            self.save(&saved)?;
+        }
+
+        self.state.remaining = remaining;
+        self.state.elapsed = elapsed;
+        self.state.expired = self.settings.mode == TimerMode::Countdown && expiries > 0;
+        self.state.phase = phase;
+        self.state.cycle = cycle;
+        self.state.saved = saved;
+        self.state.last_updated = Some(time::Instant::now());
+
+        Ok(())
+    }
+
+    fn get_strfmt_template(&self) -> anyhow::Result<Option<&str>> {
+        let format = &self.settings.format;
+        let saved = &self.state.saved;
+        let running = saved.started_at.is_some() || self.state.until.is_some();
+
+        let template: &str = match (self.state.expired, running, saved.elapsed) {
+            (true, _, _) => &format.expired,
+            (false, true, _) => &format.default,
+            (false, false, 0) => &format.idle,
+            (false, false, _) => &format.paused,
+        };
+
+        Ok(Some(template))
+    }
+
+    fn apply_strfmt_template(&self, template: &str) -> anyhow::Result<Option<String>> {
+        apply_strfmt!(
+            template,
+            "t" => match self.state.remaining {
+                // round up, so the countdown only shows 0s once it's expired
+                Some(r) => format_duration_trimmed(Duration::from_secs(r.as_secs_f64().ceil() as u64)),
+                None => format_duration_trimmed(Duration::from_secs(self.state.elapsed.as_secs())),
+            },
+            "elapsed" => format_duration_trimmed(Duration::from_secs(self.state.elapsed.as_secs())),
+            "phase" => self.state.phase.unwrap_or("N/A").to_string(),
+            "cycle" => self.state.cycle.to_string(),
+        )
+    }
+
+    fn run_action(&mut self, action: &str, _args: &[String]) -> anyhow::Result<bool> {
+        if !ACTIONS.contains(&action) {
+            return Ok(false);
+        }
+        // the countdown to `until` always runs, and must not expire again
+        if self.state.until.is_some() {
+            log::info!("ignoring {}: the timer counts down to until", action);
+            return Ok(true);
+        }
+
+        let mut saved = self.load()?;
+        let now = now_ms();
+
+        let start = |saved: &mut SavedTimer| saved.started_at = saved.started_at.or(Some(now));
+        let pause = |saved: &mut SavedTimer| {
+            if let Some(started_at) = saved.started_at.take() {
+                saved.elapsed += now.saturating_sub(started_at);
+            }
+        };
+
+        match action {
+            "timer_start" => start(&mut saved),
+            "timer_pause" => pause(&mut saved),
+            "timer_toggle" => match saved.started_at {
+                Some(_) => pause(&mut saved),
+                None => start(&mut saved),
+            },
+            "timer_reset" => saved = SavedTimer::default(),
+            _ => return Ok(false),
+        }
+
+        log::info!("ran {}: {:?}", action, saved);
+        self.save(&saved)?;
+
+        Ok(true)
+    }
+
+    fn get_action_signals(&self) -> anyhow::Result<Vec<u32>> {
+        Ok(self.settings.action_signals.values().copied().collect())
+    }
+
+    fn set_name(&mut self, name: &str) -> anyhow::Result<()> {
+        self.state.state_file = match &self.settings.state_file {
+            Some(path) => path.clone(),
+            None => default_state_dir()
+                .join("timers")
+                .join(format!("{}.yml", name)),
+        };
+        Ok(())
+    }
+
+    fn get_signal_action(&self, signal: u32) -> anyhow::Result<Option<&str>> {
+        Ok(self
+            .settings
+            .action_signals
+            .iter()
+            .find(|(_, s)| **s == signal)
+            .map(|(action, _)| action.as_str()))
+    }
+
+    impl_component_methods!(
+        set_cache,
+        get_last_updated,
+        get_refresh_interval,
+        get_signal_value,
+        get_cache,
+        default_output
+    );
+}
@@ -0,0 +1,94 @@
+use std::{fs, time};
+
+use anyhow::Context;
+use serde::Deserialize;
+use serde_yml::Value;
+use smart_default::SmartDefault;
+
+use crate::{apply_strfmt, impl_component_methods, new_from_value, utils::format_duration_trimmed};
+
+use super::Component;
+
+// Uptime /////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, SmartDefault)]
+pub struct Uptime {
+    pub state: UptimeState,
+    pub settings: UptimeSettings,
+}
+
+#[derive(Debug, SmartDefault)]
+pub struct UptimeState {
+    pub uptime: Option<time::Duration>,
+    pub last_updated: Option<time::Instant>,
+    pub cache: Option<String>,
+}
+
+#[derive(Debug, SmartDefault, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UptimeSettings {
+    #[default(60000)]
+    pub refresh_interval: u64,
+
+    #[default(12)]
+    pub signal: u32,
+
+    #[default(UptimeFormatSettings::default())]
+    pub format: UptimeFormatSettings,
+}
+
+#[derive(Debug, SmartDefault, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UptimeFormatSettings {
+    #[default(" up: {t} ")]
+    pub default: String,
+}
+
+impl Component for Uptime {
+    fn new_from_value(value: &Value) -> anyhow::Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        new_from_value!(
+            value => UptimeSettings
+        )
+    }
+
+    fn update_state(&mut self) -> anyhow::Result<()> {
+        // e.g. "350735.47 234388.90"
+        let contents = fs::read_to_string("/proc/uptime").context("failed to read /proc/uptime")?;
+        let seconds: f64 = contents
+            .split_whitespace()
+            .next()
+            .context("unexpected format in /proc/uptime")?
+            .parse()?;
+
+        self.state.uptime = Some(time::Duration::from_secs(seconds as u64));
+        self.state.last_updated = Some(time::Instant::now());
+
+        Ok(())
+    }
+
+    fn get_strfmt_template(&self) -> anyhow::Result<Option<&str>> {
+        Ok(Some(&self.settings.format.default))
+    }
+
+    fn apply_strfmt_template(&self, template: &str) -> anyhow::Result<Option<String>> {
+        apply_strfmt!(
+            template,
+            "t" => match self.state.uptime {
+                Some(t) => format_duration_trimmed(t),
+                None => "N/A".to_string(),
+            }
+        )
+    }
+
+    impl_component_methods!(
+        set_cache,
+        get_last_updated,
+        get_refresh_interval,
+        get_signal_value,
+        get_cache,
+        default_output
+    );
+}
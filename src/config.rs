@@ -1,10 +1,16 @@
-use std::{env, fs, path::PathBuf};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Context;
+use log::LevelFilter;
 use serde::Deserialize;
 use smart_default::SmartDefault;
 
-use crate::{args::Args, components::ComponentVec};
+use crate::{
+    args::Args, components::ComponentVec, output::OutputSetting, utils::deserialize_log_level,
+};
 
 pub fn default_config_path() -> PathBuf {
     let config_dir = env::var_os("XDG_CONFIG_HOME")
@@ -19,6 +25,29 @@ pub fn default_config_path() -> PathBuf {
     config_dir.join("ferristatus").join("config.yml")
 }
 
+/// Directory for state that should survive restarts, e.g. the Timer's state.
+pub fn default_state_dir() -> PathBuf {
+    let state_dir = env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            env::var_os("HOME")
+                .map(PathBuf::from)
+                .map(|p| p.join(".local").join("state"))
+                .expect("Cannot find HOME directory")
+        });
+
+    state_dir.join("ferristatus")
+}
+
+/// Directory for runtime files like the control socket.
+pub fn default_runtime_dir() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("ferristatus"),
+        // SAFETY: getuid has no memory safety requirements and can't fail.
+        None => env::temp_dir().join(format!("ferristatus-{}", unsafe { libc::getuid() })),
+    }
+}
+
 #[derive(SmartDefault, Debug, Deserialize)]
 pub struct Config {
     pub settings: Settings,
@@ -28,17 +57,50 @@ pub struct Config {
 #[derive(SmartDefault, Debug, Deserialize)]
 #[serde(default, deny_unknown_fields)]
 pub struct Settings {
-    #[default(100)]
-    pub check_interval: u64,
+    /// No longer used, components are now updated exactly when due.
+    #[default(None)]
+    pub check_interval: Option<u64>,
 
     #[default("|")]
     pub default_separator: String,
+
+    /// Reprint the line at least this often (ms), even if nothing changed.
+    #[default(None)]
+    pub force_refresh_interval: Option<u64>,
+
+    /// Where to send the status line, one or more backends.
+    pub output: OutputSetting,
+
+    /// Log level: off, error, warn, info, debug or trace (overridden by --log-level).
+    #[default(LevelFilter::Info)]
+    #[serde(deserialize_with = "deserialize_log_level")]
+    pub log_level: LevelFilter,
+
+    /// Log file, by default $XDG_STATE_HOME/ferristatus/<instance>.log.
+    #[default(None)]
+    pub log_file: Option<PathBuf>,
 }
 
 impl Config {
     pub fn new(args: &Args) -> anyhow::Result<Self> {
-        let path: PathBuf = args.config_path.clone();
-        let contents: String = read_file(&path)?;
+        Self::from_path(&args.config_path)
+    }
+
+    /// Read only the settings, e.g. to set up logging before the components
+    /// are parsed.
+    pub fn read_settings(args: &Args) -> anyhow::Result<Settings> {
+        #[derive(Deserialize)]
+        struct SettingsOnly {
+            settings: Settings,
+        }
+
+        let contents: String = read_file(&args.config_path)?;
+        let config: SettingsOnly = serde_yml::from_str(&contents)?;
+        Ok(config.settings)
+    }
+
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        let contents: String = read_file(path)?;
         Self::new_from_contents(contents)
     }
 
@@ -49,6 +111,6 @@ impl Config {
 }
 
 // read config file and return String
-fn read_file(contents: &PathBuf) -> anyhow::Result<String> {
+fn read_file(contents: &Path) -> anyhow::Result<String> {
     fs::read_to_string(contents).with_context(|| "failed to read config file")
 }
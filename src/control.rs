@@ -0,0 +1,358 @@
+use std::{
+    fmt,
+    fs::{self, DirBuilder},
+    io::{BufRead, BufReader, Read, Write},
+    os::unix::{
+        fs::DirBuilderExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        Arc, Mutex, MutexGuard,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::Context;
+use clap::Subcommand;
+
+use crate::{
+    LoopExit, collect_all_cache,
+    components::{ComponentVecType, find_components},
+    config::Config,
+    errors::MyErrors,
+    instance::socket_path,
+    output::Output,
+    signals::CONTROL,
+};
+
+/// A command sent to a running instance, one per line, e.g. "refresh time".
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum Request {
+    /// Update a component now
+    Refresh {
+        /// Component name (e.g. time) or index, as printed by `list`
+        component: String,
+    },
+    /// Re-read the config file and restart
+    Reload,
+    /// Print a component's current output
+    Get {
+        /// Component name (e.g. time) or index, as printed by `list`
+        component: String,
+    },
+    /// Replace the text of a Text component
+    SetText {
+        /// Component name (e.g. text) or index, as printed by `list`
+        component: String,
+        #[arg(allow_hyphen_values = true)]
+        text: String,
+    },
+    /// List the components: index, name and current output
+    List,
+    /// Stop the running instance
+    Quit,
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Request::Refresh { component } => write!(f, "refresh {}", component),
+            Request::Reload => write!(f, "reload"),
+            Request::Get { component } => write!(f, "get {}", component),
+            Request::SetText { component, text } => write!(f, "set-text {} {}", component, text),
+            Request::List => write!(f, "list"),
+            Request::Quit => write!(f, "quit"),
+        }
+    }
+}
+
+impl FromStr for Request {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> anyhow::Result<Self> {
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let component = || -> anyhow::Result<String> {
+            anyhow::ensure!(!rest.is_empty(), "{} needs a component", command);
+            Ok(rest.to_string())
+        };
+
+        Ok(match command {
+            "refresh" => Request::Refresh {
+                component: component()?,
+            },
+            "get" => Request::Get {
+                component: component()?,
+            },
+            "set-text" => {
+                // the text is the rest of the line, spaces included
+                let (component, text) = rest.split_once(' ').unwrap_or((rest, ""));
+                anyhow::ensure!(!component.is_empty(), "set-text needs a component");
+                Request::SetText {
+                    component: component.to_string(),
+                    text: text.to_string(),
+                }
+            }
+            "reload" => Request::Reload,
+            "list" => Request::List,
+            "quit" => Request::Quit,
+            _ => anyhow::bail!("unknown command: {}", command),
+        })
+    }
+}
+
+/// A request read from a client, which gets the reply.
+pub struct ControlRequest {
+    request: Request,
+    stream: UnixStream,
+}
+
+/// Send a reply and close the connection. Errors start with "error: ".
+fn reply(mut stream: UnixStream, result: anyhow::Result<String>) {
+    let text = match result {
+        Ok(text) => text,
+        Err(e) => format!("error: {:#}\n", e),
+    };
+    let _ = stream.write_all(text.as_bytes()); // We don't care if the client went away
+}
+
+/// The control socket of a running instance. Requests are read by a thread and
+/// handled by the main loop, which gets `signals::CONTROL` when one arrives.
+pub struct Control {
+    path: PathBuf,
+    requests: Receiver<ControlRequest>,
+    names: Vec<String>,
+    config_path: PathBuf,
+    /// The client that asked for a reload. It's only replied to if restarting
+    /// fails, otherwise the connection is closed by the restart.
+    reload_client: Mutex<Option<UnixStream>>,
+}
+
+impl Control {
+    pub fn spawn(
+        path: &Path,
+        names: Vec<String>,
+        config_path: PathBuf,
+        sender: Sender<u32>,
+    ) -> anyhow::Result<Self> {
+        if let Some(dir) = path.parent() {
+            DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        if path.exists() {
+            anyhow::ensure!(
+                UnixStream::connect(path).is_err(),
+                "another instance is listening on {}",
+                path.display()
+            );
+            // left behind by an instance that didn't exit cleanly
+            fs::remove_file(path)
+                .with_context(|| format!("failed to remove old socket {}", path.display()))?;
+        }
+        let listener = UnixListener::bind(path)
+            .with_context(|| format!("failed to listen on {}", path.display()))?;
+        log::info!("listening for control requests on {}", path.display());
+
+        let (request_tx, request_rx) = mpsc::channel();
+        thread::Builder::new()
+            .name("control".into())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    // don't let a stuck client block everyone else
+                    let _ = stream.set_read_timeout(Some(Duration::from_secs(1)));
+                    let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+
+                    let mut line = String::new();
+                    if BufReader::new(&stream).read_line(&mut line).is_err() {
+                        continue;
+                    }
+                    let request = match line.trim_end_matches(['\r', '\n']).parse() {
+                        Ok(r) => r,
+                        Err(e) => {
+                            reply(stream, Err(e));
+                            continue;
+                        }
+                    };
+
+                    if request_tx.send(ControlRequest { request, stream }).is_err()
+                        || sender.send(CONTROL).is_err()
+                    {
+                        return;
+                    }
+                }
+            })
+            .context("failed to spawn control thread")?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            requests: request_rx,
+            names,
+            config_path,
+            reload_client: Mutex::new(None),
+        })
+    }
+
+    /// Tell the client that asked for the reload that it failed.
+    pub fn reload_failed(&self, e: anyhow::Error) {
+        if let Some(stream) = self.reload_client.lock().ok().and_then(|mut c| c.take()) {
+            reply(stream, Err(e));
+        }
+    }
+
+    /// Handle every pending request, returning whether the main loop should stop.
+    pub fn handle_pending(
+        &self,
+        components: &Arc<Mutex<ComponentVecType>>,
+        output: &mut Output,
+    ) -> anyhow::Result<Option<LoopExit>> {
+        let components_guard: MutexGuard<'_, ComponentVecType> =
+            components.lock().map_err(MyErrors::from_poison_error)?;
+
+        while let Ok(ControlRequest { request, stream }) = self.requests.try_recv() {
+            log::info!("control request: {}", request);
+
+            let result = self.handle(&request, &components_guard, output);
+            let exit = match (&request, &result) {
+                (Request::Reload, Ok(_)) => Some(LoopExit::Reload),
+                (Request::Quit, _) => Some(LoopExit::Quit),
+                _ => None,
+            };
+            match exit {
+                Some(LoopExit::Reload) => {
+                    *self
+                        .reload_client
+                        .lock()
+                        .map_err(MyErrors::from_poison_error)? = Some(stream)
+                }
+                _ => reply(stream, result),
+            }
+
+            if exit.is_some() {
+                return Ok(exit);
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn handle(
+        &self,
+        request: &Request,
+        components: &MutexGuard<'_, ComponentVecType>,
+        output: &mut Output,
+    ) -> anyhow::Result<String> {
+        let mut reply = String::new();
+        match request {
+            Request::Refresh { component } => {
+                for i in find_components(&self.names, component)? {
+                    components[i]
+                        .lock()
+                        .map_err(MyErrors::from_poison_error)?
+                        .update_and_compare()
+                        .with_context(|| format!("failed to refresh {}", self.names[i]))?;
+                }
+                output.print(collect_all_cache(components)?);
+            }
+            Request::Get { component } => {
+                for i in find_components(&self.names, component)? {
+                    let c_guard = components[i].lock().map_err(MyErrors::from_poison_error)?;
+                    reply += &format!("{}\n", c_guard.get_cache()?.unwrap_or_default());
+                }
+            }
+            Request::SetText { component, text } => {
+                for i in find_components(&self.names, component)? {
+                    let mut c_guard = components[i].lock().map_err(MyErrors::from_poison_error)?;
+                    anyhow::ensure!(
+                        c_guard.set_text(text)?,
+                        "{} isn't a text component",
+                        self.names[i]
+                    );
+                }
+                output.print(collect_all_cache(components)?);
+            }
+            Request::List => {
+                for (i, (c, name)) in components.iter().zip(&self.names).enumerate() {
+                    let c_guard = c.lock().map_err(MyErrors::from_poison_error)?;
+                    reply += &format!(
+                        "{}\t{}\t{}\n",
+                        i,
+                        name,
+                        c_guard.get_cache()?.unwrap_or_default()
+                    );
+                }
+            }
+            Request::Reload => {
+                // keep running with the current config if the new one is broken
+                Config::from_path(&self.config_path).context("not reloading")?;
+            }
+            Request::Quit => {}
+        }
+
+        Ok(reply)
+    }
+}
+
+impl Drop for Control {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Send a request to the running instance called `instance` and print its reply.
+pub fn run_ctl(instance: &str, request: &Request) -> anyhow::Result<()> {
+    print!("{}", send_request(instance, request)?);
+    Ok(())
+}
+
+/// Send a request to the running instance called `instance`, returning its reply.
+pub fn send_request(instance: &str, request: &Request) -> anyhow::Result<String> {
+    let path = socket_path(instance);
+    let mut stream = UnixStream::connect(&path).with_context(|| {
+        format!(
+            "failed to connect to {}, is ferristatus running?",
+            path.display()
+        )
+    })?;
+    writeln!(stream, "{}", request)?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    if let Some(e) = reply.strip_prefix("error: ") {
+        anyhow::bail!("{}", e.trim_end());
+    }
+
+    Ok(reply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_requests() -> anyhow::Result<()> {
+        for request in [
+            Request::Refresh {
+                component: "time".into(),
+            },
+            Request::SetText {
+                component: "3".into(),
+                text: " a  b ".into(),
+            },
+            Request::List,
+        ] {
+            assert_eq!(request.to_string().parse::<Request>()?, request);
+        }
+        assert!("get".parse::<Request>().is_err());
+        assert!("frobnicate x".parse::<Request>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,732 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use anyhow::Context;
+use serde_yml::{Mapping, Value};
+
+/// A config translated from another status bar.
+#[derive(Debug, Default)]
+pub struct Imported {
+    /// The ferristatus config, as YAML.
+    pub yaml: String,
+    /// Everything that couldn't be translated (exactly), one entry per setting.
+    pub untranslated: Vec<String>,
+}
+
+/// Translate the config at `path` (in the given `format`) and print it to
+/// stdout, reporting anything that couldn't be translated on stderr.
+pub fn run_import(format: &str, path: &Path) -> anyhow::Result<()> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+
+    let imported = match format {
+        "i3blocks" => import_i3blocks(&contents)?,
+        "dwmblocks" => import_dwmblocks(&contents)?,
+        "slstatus" => import_slstatus(&contents)?,
+        _ => anyhow::bail!("unknown format: {}", format),
+    };
+
+    print!("{}", imported.yaml);
+    for entry in &imported.untranslated {
+        eprintln!("not translated: {}", entry);
+    }
+
+    Ok(())
+}
+
+/// Build the YAML for a config with these components.
+fn config_yaml(components: Vec<Value>) -> anyhow::Result<String> {
+    let mut config = Mapping::new();
+    config.insert("settings".into(), Value::Mapping(Mapping::new()));
+    config.insert("components".into(), Value::Sequence(components));
+    Ok(serde_yml::to_string(&config)?)
+}
+
+/// A single-key mapping, e.g. `Command: {...}`.
+fn component(name: &str, settings: Value) -> Value {
+    let mut mapping = Mapping::new();
+    mapping.insert(name.into(), settings);
+    Value::Mapping(mapping)
+}
+
+/// Escape braces, so text can be used literally in a strfmt template.
+fn escape_template(text: &str) -> String {
+    text.replace('{', "{{").replace('}', "}}")
+}
+
+/// Only show the first line of a command's output, after `label`, since
+/// scripts for other bars may print more (e.g. i3blocks' short_text and color).
+fn insert_first_line_format(settings: &mut Mapping, label: &str) {
+    settings.insert("parser".into(), "regex".into());
+    settings.insert("pattern".into(), "^(?P<first_line>.*)".into());
+
+    let template = format!("{}{{first_line}}", escape_template(label));
+    let mut format = Mapping::new();
+    format.insert("default".into(), template.as_str().into());
+    format.insert("stale".into(), template.as_str().into());
+    settings.insert("format".into(), Value::Mapping(format));
+}
+
+// i3blocks ///////////////////////////////////////////////////////////////////
+
+/// A `[name]` section of an i3blocks config, with the global properties applied.
+#[derive(Debug)]
+struct I3Block {
+    name: String,
+    properties: Vec<(String, String)>,
+}
+
+impl I3Block {
+    fn get(&self, key: &str) -> Option<&str> {
+        // later properties override earlier (global) ones
+        self.properties
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn parse_i3blocks(contents: &str) -> Vec<I3Block> {
+    let mut globals: Vec<(String, String)> = Vec::new();
+    let mut blocks: Vec<I3Block> = Vec::new();
+
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            blocks.push(I3Block {
+                name: name.trim().to_string(),
+                properties: globals.clone(),
+            });
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let property = (key.trim().to_string(), value.trim().to_string());
+        match blocks.last_mut() {
+            Some(block) => block.properties.push(property),
+            None => globals.push(property),
+        }
+    }
+
+    blocks
+}
+
+/// A component name made from a block name: without spaces, not a number and
+/// not in `names` (ignoring case), which it's added to.
+fn component_name(block_name: &str, names: &mut HashSet<String>) -> String {
+    let mut base: String = block_name
+        .chars()
+        .map(|c| if c.is_whitespace() { '_' } else { c })
+        .collect();
+    if base.is_empty() || base.parse::<usize>().is_ok() {
+        base = format!("block{}", base);
+    }
+
+    let name = (1..)
+        .map(|n| match n {
+            1 => base.clone(),
+            n => format!("{}_{}", base, n),
+        })
+        .find(|name| !names.contains(&name.to_lowercase()))
+        .unwrap_or_default();
+    names.insert(name.to_lowercase());
+    name
+}
+
+/// Convert an i3blocks config into a ferristatus config. Blocks become Command
+/// components (or Text, for static blocks), separated by Text components.
+pub fn import_i3blocks(contents: &str) -> anyhow::Result<Imported> {
+    let mut components: Vec<Value> = Vec::new();
+    let mut untranslated: Vec<String> = Vec::new();
+
+    let mut signals: HashSet<u32> = HashSet::new();
+    // the separators and static blocks are unnamed Text components
+    let mut names: HashSet<String> = HashSet::from(["text".to_string()]);
+    let blocks = parse_i3blocks(contents);
+    for (i, block) in blocks.iter().enumerate() {
+        let mut report = |what: String| untranslated.push(format!("[{}] {}", block.name, what));
+
+        let label = block.get("label").unwrap_or_default();
+
+        match (block.get("command"), block.get("full_text")) {
+            (Some(command), _) => {
+                let mut settings = Mapping::new();
+                settings.insert("shell_command".into(), command.into());
+                // the name is passed as $BLOCK_NAME, unless it had to be changed
+                let name = component_name(&block.name, &mut names);
+                if name != block.name {
+                    let mut env = Mapping::new();
+                    env.insert("BLOCK_NAME".into(), block.name.as_str().into());
+                    settings.insert("env".into(), Value::Mapping(env));
+                }
+                settings.insert("name".into(), name.into());
+                if let Some(instance) = block.get("instance") {
+                    settings.insert("instance".into(), instance.into());
+                }
+
+                match block.get("interval") {
+                    Some("persist") => {
+                        settings.insert("persistent".into(), true.into());
+                    }
+                    Some("repeat") => {
+                        settings.insert("persistent".into(), true.into());
+                        report("interval=repeat: approximated with persistent: true".into());
+                    }
+                    Some(secs) if secs.parse::<u64>().is_ok_and(|s| s > 0) => {
+                        let ms = secs.parse::<u64>().unwrap_or_default() * 1000;
+                        settings.insert("refresh_interval".into(), ms.into());
+                    }
+                    other => {
+                        // only run on startup and when signalled
+                        settings.insert("refresh_interval".into(), u64::from(u32::MAX).into());
+                        if let Some(other) = other.filter(|o| *o != "once") {
+                            report(format!("interval={}: treated as once", other));
+                        }
+                    }
+                }
+
+                if let Some(signal) = block.get("signal") {
+                    match signal.parse::<u32>() {
+                        // ferristatus signals are unique, the first block keeps it
+                        Ok(n) => match signals.insert(n) {
+                            true => {
+                                settings.insert("signal".into(), n.into());
+                            }
+                            false => report(format!("signal={}: already used", signal)),
+                        },
+                        Err(_) => report(format!("signal={}: not a number", signal)),
+                    }
+                }
+
+                let variable = match (block.get("format"), block.get("interval")) {
+                    (Some("json"), _) => {
+                        settings.insert("parser".into(), "json".into());
+                        Some("{full_text}")
+                    }
+                    // persistent commands already use one line at a time
+                    (_, Some("persist")) => Some("{output}"),
+                    _ => None,
+                };
+                match variable {
+                    Some(variable) => {
+                        let template = format!("{}{}", escape_template(label), variable);
+                        let mut format = Mapping::new();
+                        format.insert("default".into(), template.as_str().into());
+                        format.insert("stale".into(), template.as_str().into());
+                        settings.insert("format".into(), Value::Mapping(format));
+                    }
+                    None => insert_first_line_format(&mut settings, label),
+                }
+
+                components.push(component("Command", Value::Mapping(settings)));
+            }
+            (None, Some(full_text)) => {
+                components.push(component("text", format!("{}{}", label, full_text).into()));
+            }
+            (None, None) => {
+                report("no command or full_text, skipped".into());
+                continue;
+            }
+        }
+
+        for (key, value) in &block.properties {
+            match key.as_str() {
+                "command" | "full_text" | "interval" | "signal" | "label" | "instance"
+                | "separator" => {}
+                "format" if value == "json" => {}
+                "color" => report(format!(
+                    "color={}: colors aren't supported in plain text output",
+                    value
+                )),
+                _ => report(format!("{}={}", key, value)),
+            }
+        }
+
+        // i3bar draws a separator after each block unless separator=false
+        if block.get("separator") != Some("false") && i + 1 < blocks.len() {
+            components.push(component("text", "|".into()));
+        }
+    }
+
+    Ok(Imported {
+        yaml: config_yaml(components)?,
+        untranslated,
+    })
+}
+
+// C headers //////////////////////////////////////////////////////////////////
+
+/// Just enough of C to read the config tables of dwmblocks and slstatus.
+#[derive(Debug, Clone, PartialEq)]
+enum CToken {
+    /// A string literal, with adjacent literals joined and escapes decoded.
+    Str(String),
+    /// An identifier or number.
+    Word(String),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize_c(source: &str) -> Vec<CToken> {
+    let mut tokens: Vec<CToken> = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut line_start = true;
+
+    while let Some(c) = chars.next() {
+        match c {
+            // preprocessor lines
+            '#' if line_start => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            '"' => {
+                let mut literal = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some('n') => literal.push('\n'),
+                            Some('t') => literal.push('\t'),
+                            Some('0') => literal.push('\0'),
+                            Some(other) => literal.push(other),
+                            None => break,
+                        },
+                        c => literal.push(c),
+                    }
+                }
+                match tokens.last_mut() {
+                    Some(CToken::Str(previous)) => previous.push_str(&literal),
+                    _ => tokens.push(CToken::Str(literal)),
+                }
+            }
+            '\'' => {
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if c == '\'' && prev != '\\' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            '{' => tokens.push(CToken::Open),
+            '}' => tokens.push(CToken::Close),
+            ',' => tokens.push(CToken::Comma),
+            c if c.is_alphanumeric() || c == '_' || c == '-' || c == '.' => {
+                let mut word = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_' || c == '.') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(CToken::Word(word));
+            }
+            _ => {}
+        }
+        line_start = c == '\n' || (line_start && c.is_whitespace());
+    }
+
+    tokens
+}
+
+/// The token after the variable `name`, e.g. the value of `int interval = 1000;`.
+fn c_value<'a>(tokens: &'a [CToken], name: &str) -> Option<&'a CToken> {
+    let i = tokens
+        .iter()
+        .position(|t| *t == CToken::Word(name.to_string()))?;
+    tokens.get(i + 1)
+}
+
+/// The entries of the array `name`, e.g. `{"a", 1}, {"b", 2}`, each as a list of fields.
+fn c_array(tokens: &[CToken], name: &str) -> anyhow::Result<Vec<Vec<CToken>>> {
+    let start = tokens
+        .iter()
+        .position(|t| *t == CToken::Word(name.to_string()))
+        .with_context(|| format!("couldn't find the {} array", name))?;
+
+    let mut entries: Vec<Vec<CToken>> = Vec::new();
+    let mut depth = 0;
+    for token in &tokens[start..] {
+        match token {
+            CToken::Open => {
+                depth += 1;
+                if depth == 2 {
+                    entries.push(Vec::new());
+                }
+            }
+            CToken::Close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(entries);
+                }
+            }
+            CToken::Comma => {}
+            field if depth == 2 => {
+                if let Some(entry) = entries.last_mut() {
+                    entry.push(field.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    anyhow::bail!("the {} array isn't closed", name)
+}
+
+fn c_str(token: Option<&CToken>) -> Option<&str> {
+    match token {
+        Some(CToken::Str(s)) => Some(s),
+        _ => None,
+    }
+}
+
+fn c_number(token: Option<&CToken>) -> Option<u64> {
+    match token {
+        Some(CToken::Word(w)) => w.parse().ok(),
+        _ => None,
+    }
+}
+
+// dwmblocks //////////////////////////////////////////////////////////////////
+
+/// Convert a dwmblocks `blocks.h` into a ferristatus config. Each block becomes
+/// a Command component, separated by `delim`.
+pub fn import_dwmblocks(contents: &str) -> anyhow::Result<Imported> {
+    let tokens = tokenize_c(contents);
+    // "\0" means no delimiter
+    let delim = c_str(c_value(&tokens, "delim"))
+        .unwrap_or(" ")
+        .trim_end_matches('\0')
+        .to_string();
+
+    let mut components: Vec<Value> = Vec::new();
+    let mut untranslated: Vec<String> = Vec::new();
+
+    let mut signals: HashSet<u64> = HashSet::new();
+    let blocks = c_array(&tokens, "blocks")?;
+    for (i, block) in blocks.iter().enumerate() {
+        let (Some(icon), Some(command)) = (c_str(block.first()), c_str(block.get(1))) else {
+            untranslated.push(format!(
+                "block {}: expected {{icon, command, interval, signal}}",
+                i
+            ));
+            continue;
+        };
+
+        let mut settings = Mapping::new();
+        settings.insert("shell_command".into(), command.into());
+        match c_number(block.get(2)) {
+            // 0 means only on signal
+            Some(0) | None => {
+                settings.insert("refresh_interval".into(), u64::from(u32::MAX).into())
+            }
+            Some(secs) => settings.insert("refresh_interval".into(), (secs * 1000).into()),
+        };
+        if let Some(signal) = c_number(block.get(3)).filter(|s| *s > 0) {
+            // ferristatus signals are unique, the first block keeps it
+            match signals.insert(signal) {
+                true => settings.insert("signal".into(), signal.into()),
+                false => {
+                    untranslated.push(format!("block {}: signal {} is already used", i, signal));
+                    None
+                }
+            };
+        }
+        insert_first_line_format(&mut settings, icon);
+
+        if i > 0 && !delim.is_empty() {
+            components.push(component("text", delim.as_str().into()));
+        }
+        components.push(component("Command", Value::Mapping(settings)));
+    }
+
+    Ok(Imported {
+        yaml: config_yaml(components)?,
+        untranslated,
+    })
+}
+
+// slstatus ///////////////////////////////////////////////////////////////////
+
+/// Turn a printf format with a single `%s` into a template, with `%s` replaced by `vars`.
+fn printf_to_template(format: &str, vars: &str) -> String {
+    escape_template(format)
+        .replace("%s", vars)
+        .replace("%%", "%")
+}
+
+/// Shell equivalents of slstatus functions without a native component.
+fn slstatus_shell_command(function: &str, argument: Option<&str>) -> Option<String> {
+    let arg = argument.unwrap_or_default();
+    let command = match function {
+        "run_command" => arg.to_string(),
+        "ram_perc" => "free | awk '/^Mem/ {printf \"%d\", $3 / $2 * 100}'".to_string(),
+        "ram_used" => "free -h | awk '/^Mem/ {print $3}'".to_string(),
+        "ram_free" => "free -h | awk '/^Mem/ {print $4}'".to_string(),
+        "ram_total" => "free -h | awk '/^Mem/ {print $2}'".to_string(),
+        "swap_perc" => "free | awk '/^Swap/ {printf \"%d\", $2 ? $3 / $2 * 100 : 0}'".to_string(),
+        "cpu_perc" => "top -bn1 | awk '/^%Cpu/ {printf \"%d\", 100 - $8}'".to_string(),
+        "disk_perc" => format!("df --output=pcent '{}' | tail -n1 | tr -d ' %'", arg),
+        "disk_free" => format!("df -h --output=avail '{}' | tail -n1 | tr -d ' '", arg),
+        "disk_used" => format!("df -h --output=used '{}' | tail -n1 | tr -d ' '", arg),
+        "temp" => format!("echo $(( $(cat '{}') / 1000 ))", arg),
+        "ipv4" => format!(
+            "ip -4 -o addr show '{}' | awk '{{print $4}}' | cut -d/ -f1",
+            arg
+        ),
+        "wifi_essid" => format!("iw dev '{}' link | awk '/SSID/ {{print $2}}'", arg),
+        "hostname" => "uname -n".to_string(),
+        "kernel_release" => "uname -r".to_string(),
+        "username" => "id -un".to_string(),
+        "keymap" => "setxkbmap -query | awk '/layout/ {print $2}'".to_string(),
+        _ => return None,
+    };
+    Some(command)
+}
+
+/// Convert an slstatus `config.h` into a ferristatus config. Each entry of
+/// `args` becomes a native component where one exists, and a Command otherwise.
+pub fn import_slstatus(contents: &str) -> anyhow::Result<Imported> {
+    let tokens = tokenize_c(contents);
+    let interval = c_number(c_value(&tokens, "interval")).unwrap_or(1000);
+
+    let mut components: Vec<Value> = Vec::new();
+    let mut untranslated: Vec<String> = Vec::new();
+
+    for (i, entry) in c_array(&tokens, "args")?.iter().enumerate() {
+        let (Some(CToken::Word(function)), Some(format)) = (entry.first(), c_str(entry.get(1)))
+        else {
+            untranslated.push(format!(
+                "args[{}]: expected {{function, format, argument}}",
+                i
+            ));
+            continue;
+        };
+        let argument = c_str(entry.get(2));
+        let mut report =
+            |what: &str| untranslated.push(format!("args[{}] {}: {}", i, function, what));
+
+        let mut settings = Mapping::new();
+        settings.insert("refresh_interval".into(), interval.into());
+        let mut format_settings = Mapping::new();
+
+        let name = match function.as_str() {
+            "datetime" => {
+                settings.insert("time".into(), argument.unwrap_or("%c").into());
+                format_settings.insert("default".into(), printf_to_template(format, "{t}").into());
+                "Time"
+            }
+            "battery_perc" | "battery_remaining" | "battery_state" => {
+                if let Some(battery) = argument {
+                    let path = format!("/sys/class/power_supply/{}", battery);
+                    settings.insert("path".into(), path.into());
+                }
+                let var = match function.as_str() {
+                    "battery_perc" => "{p}",
+                    "battery_remaining" => "{t}",
+                    _ => {
+                        report("shown as {status} (e.g. Discharging) instead of a symbol");
+                        "{status}"
+                    }
+                };
+                let template = printf_to_template(format, var);
+                for state in ["default", "full", "charging", "not_charging"] {
+                    format_settings.insert(state.into(), template.as_str().into());
+                }
+                "Battery"
+            }
+            "load_avg" => {
+                format_settings.insert(
+                    "default".into(),
+                    printf_to_template(format, "{1m} {5m} {15m}").into(),
+                );
+                "Load"
+            }
+            "uptime" => {
+                format_settings.insert("default".into(), printf_to_template(format, "{t}").into());
+                "Uptime"
+            }
+            "vol_perc" => {
+                report("uses the default ALSA mixer, the argument is ignored");
+                let template = printf_to_template(format, "{p}");
+                format_settings.insert("default".into(), template.as_str().into());
+                format_settings.insert("muted".into(), template.as_str().into());
+                "Alsa"
+            }
+            _ => match slstatus_shell_command(function, argument) {
+                Some(command) => {
+                    settings.insert("shell_command".into(), command.into());
+                    settings.insert("trim".into(), true.into());
+                    let template = printf_to_template(format, "{output}");
+                    format_settings.insert("default".into(), template.as_str().into());
+                    format_settings.insert("stale".into(), template.as_str().into());
+                    "Command"
+                }
+                None => {
+                    report("no equivalent, skipped");
+                    continue;
+                }
+            },
+        };
+
+        settings.insert("format".into(), Value::Mapping(format_settings));
+        components.push(component(name, Value::Mapping(settings)));
+    }
+
+    Ok(Imported {
+        yaml: config_yaml(components)?,
+        untranslated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_i3blocks() {
+        let config = "\
+# globals
+separator_block_width=15
+color=#ffffff
+
+[volume]
+command=~/.bin/volume
+instance=Master
+interval=once
+signal=10
+label=VOL {
+
+[time]
+command=date '+%H:%M'
+interval=5
+separator=false
+
+[static]
+full_text=hello
+
+[time]
+command=date '+%s'
+separator=false
+
+[my disk]
+command=df
+separator=false
+";
+        let imported = import_i3blocks(config).unwrap();
+        let value: Value = serde_yml::from_str(&imported.yaml).unwrap();
+        let components = value["components"].as_sequence().unwrap();
+
+        // volume, separator, time, static, separator, time, disk (no separator
+        // after either time or disk)
+        assert_eq!(components.len(), 7);
+        let volume = &components[0]["Command"];
+        assert_eq!(volume["name"], "volume");
+        assert!(volume["env"].is_null());
+        assert_eq!(volume["shell_command"], "~/.bin/volume");
+        assert_eq!(volume["instance"], "Master");
+        assert_eq!(volume["signal"], 10);
+        assert_eq!(volume["format"]["default"], "VOL {{{first_line}");
+        assert_eq!(components[1]["text"], "|");
+        assert_eq!(components[2]["Command"]["refresh_interval"], 5000);
+        assert_eq!(components[3]["text"], "hello");
+        // names are unique and without spaces, $BLOCK_NAME stays the same
+        assert_eq!(components[5]["Command"]["name"], "time_2");
+        assert_eq!(components[5]["Command"]["env"]["BLOCK_NAME"], "time");
+        assert_eq!(components[6]["Command"]["name"], "my_disk");
+        assert_eq!(components[6]["Command"]["env"]["BLOCK_NAME"], "my disk");
+
+        // the global color and separator_block_width, for each of the 5 blocks
+        assert_eq!(imported.untranslated.len(), 10);
+
+        // the result is a valid config
+        crate::config::Config::new_from_contents(imported.yaml).unwrap();
+    }
+
+    #[test]
+    fn imports_c_headers() {
+        let blocks = r#"
+//Modify this file to change what commands output to your statusbar, and recompile using the make command.
+static const Block blocks[] = {
+	/*Icon*/	/*Command*/		/*Update Interval*/	/*Update Signal*/
+	{"Mem:", "free -h | awk '/^Mem/ { print $3\"/\"$2 }' | sed s/i//g",	30,		0},
+	{"", "date '+%b %d (%a) %I:%M%p'"
+	     " | tr a-z A-Z",					5,		10},
+};
+
+//sets delimiter between status commands. NULL character ('\0') means no delimiter.
+static char delim[] = " | ";
+static unsigned int delimLen = 5;
+"#;
+        let imported = import_dwmblocks(blocks).unwrap();
+        let value: Value = serde_yml::from_str(&imported.yaml).unwrap();
+        let components = value["components"].as_sequence().unwrap();
+        assert_eq!(components.len(), 3);
+        assert_eq!(
+            components[0]["Command"]["shell_command"],
+            "free -h | awk '/^Mem/ { print $3\"/\"$2 }' | sed s/i//g"
+        );
+        assert_eq!(components[1]["text"], " | ");
+        let date = &components[2]["Command"];
+        assert_eq!(
+            date["shell_command"],
+            "date '+%b %d (%a) %I:%M%p' | tr a-z A-Z"
+        );
+        assert_eq!(date["refresh_interval"], 5000);
+        assert_eq!(date["signal"], 10);
+        crate::config::Config::new_from_contents(imported.yaml).unwrap();
+
+        let config = r#"
+/* interval between updates (in ms) */
+const unsigned int interval = 2000;
+
+static const struct arg args[] = {
+	/* function format          argument */
+	{ battery_perc, "BAT %s%% ", "BAT1" },
+	{ ram_perc,     "[RAM {%s}] ", NULL },
+	{ entropy,      "%s",          NULL },
+	{ datetime,     "%s",           "%F %T" },
+};
+"#;
+        let imported = import_slstatus(config).unwrap();
+        let value: Value = serde_yml::from_str(&imported.yaml).unwrap();
+        let components = value["components"].as_sequence().unwrap();
+        assert_eq!(components.len(), 3);
+        let battery = &components[0]["Battery"];
+        assert_eq!(battery["path"], "/sys/class/power_supply/BAT1");
+        assert_eq!(battery["format"]["charging"], "BAT {p}% ");
+        assert_eq!(battery["refresh_interval"], 2000);
+        assert_eq!(
+            components[1]["Command"]["format"]["default"],
+            "[RAM {{{output}}}] "
+        );
+        assert_eq!(components[2]["Time"]["time"], "%F %T");
+        assert_eq!(imported.untranslated.len(), 1);
+        crate::config::Config::new_from_contents(imported.yaml).unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::config::{default_runtime_dir, default_state_dir};
+
+/// The instance name used when `--instance` isn't given.
+pub const DEFAULT_INSTANCE: &str = "default";
+
+/// Path of the pid file of the instance called `instance`.
+pub fn pid_path(instance: &str) -> PathBuf {
+    default_runtime_dir().join(format!("{}.pid", instance))
+}
+
+/// Path of the control socket of the instance called `instance`.
+pub fn socket_path(instance: &str) -> PathBuf {
+    default_runtime_dir().join(format!("{}.sock", instance))
+}
+
+/// Default path of the log file of the instance called `instance`.
+pub fn log_path(instance: &str) -> PathBuf {
+    default_state_dir().join(format!("{}.log", instance))
+}
+
+/// Read the pid from a pid file.
+pub fn read_pid(path: &Path) -> Option<libc::pid_t> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn is_alive(pid: libc::pid_t) -> bool {
+    // SAFETY: kill has no memory safety requirements, signal 0 only checks the pid.
+    let ok = unsafe { libc::kill(pid, 0) } == 0;
+    // EPERM means it exists, but belongs to someone else
+    ok || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Whether `pid` is running the same program as we are, and not some other
+/// process that got the pid of an instance that's gone.
+fn is_ferristatus(pid: libc::pid_t) -> bool {
+    let comm = |pid: &str| fs::read_to_string(format!("/proc/{}/comm", pid)).ok();
+    comm(&pid.to_string()).is_some_and(|c| Some(c) == comm("self"))
+}
+
+/// Names of the running instances, or just `instance` if it's running.
+pub fn running_instances(instance: Option<&str>) -> Vec<String> {
+    let paths: Vec<PathBuf> = match instance {
+        Some(name) => vec![pid_path(name)],
+        None => match fs::read_dir(default_runtime_dir()) {
+            Ok(dir) => dir
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "pid"))
+                .collect(),
+            Err(_) => Vec::new(),
+        },
+    };
+
+    paths
+        .iter()
+        .filter(|p| read_pid(p).is_some_and(|pid| is_alive(pid) && is_ferristatus(pid)))
+        .filter_map(|p| Some(p.file_stem()?.to_string_lossy().to_string()))
+        .collect()
+}
+
+/// Remove the pid files and control sockets of instances that are no longer
+/// running, e.g. after a crash or a SIGKILL, along with pid files from older
+/// versions that used /tmp/ferristatus-XXXXXX.pid. Pid files that can't be
+/// read yet, e.g. of an instance that's starting, are left alone.
+pub fn remove_stale_files() {
+    let legacy = fs::read_dir("/tmp")
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            let name = p.file_name().unwrap_or_default().to_string_lossy();
+            name.starts_with("ferristatus-") && name.ends_with(".pid")
+        });
+    let current = fs::read_dir(default_runtime_dir())
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "pid"));
+
+    for path in legacy.chain(current) {
+        match read_pid(&path) {
+            Some(pid) if !is_alive(pid) || !is_ferristatus(pid) => {}
+            _ => continue,
+        }
+
+        log::info!("removing stale {}", path.display());
+        let _ = fs::remove_file(&path);
+        if path.starts_with(default_runtime_dir()) {
+            let _ = fs::remove_file(path.with_extension("sock"));
+        }
+    }
+}
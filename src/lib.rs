@@ -1,87 +1,136 @@
 use std::{
-    fs::File,
+    collections::BTreeSet,
+    env,
+    fs::{self, DirBuilder, OpenOptions},
     io,
-    sync::{Arc, Mutex, MutexGuard},
-    thread,
-    time::Duration,
+    os::unix::{fs::DirBuilderExt, process::CommandExt},
+    process,
+    sync::{
+        Arc, Mutex, MutexGuard,
+        mpsc::{self, Receiver, RecvTimeoutError},
+    },
+    time::Instant,
 };
 
 use anyhow::Context;
 use args::Args;
 use components::ComponentVecType;
-use config::Config;
+use config::{Config, Settings};
+use control::{Control, Request, send_request};
 use env_logger::{Builder, Env, Target};
 use errors::MyErrors;
-use log::LevelFilter;
+use instance::{log_path, pid_path, remove_stale_files, running_instances, socket_path};
+use output::Output;
 use pidfile::PidFile;
-use rand::Rng;
-use signals::spawn_signal_responder_thread;
+use signals::{CONTROL, WAKE, signals_watch};
+use watch::spawn_watcher_threads;
 
 pub mod args;
 pub mod components;
 pub mod config;
+pub mod control;
 pub mod errors;
+pub mod import;
+pub mod instance;
+pub mod output;
 pub mod signals;
 pub mod utils;
+pub mod watch;
+
+/// Size above which the log file is moved to `<log file>.old` on startup.
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// Initialize logging to the instance's log file. It's appended to, so the
+/// logs from before a restart or reload are kept, until it grows over
+/// `MAX_LOG_SIZE` and replaces the previous `.old` file.
+pub fn init_logger(args: &Args, settings: &Settings) -> anyhow::Result<()> {
+    let path = settings
+        .log_file
+        .clone()
+        .unwrap_or_else(|| log_path(args.instance_name()));
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    if fs::metadata(&path).is_ok_and(|m| m.is_file() && m.len() > MAX_LOG_SIZE) {
+        let mut old = path.clone().into_os_string();
+        old.push(".old");
+        fs::rename(&path, &old)
+            .with_context(|| format!("failed to rotate log file {}", path.display()))?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("failed to open log file {}", path.display()))?;
 
-/// Initialize logging support (to log file)
-/// TODO: currently overwrites the file, so doesn't support multiple instances
-/// (maybe use it in conjunction with the pid file by having same 6 digit ID?
-/// and have it auto-dropped?)
-pub fn init_logger() -> anyhow::Result<()> {
-    let path = "/tmp/ferristatus.log";
-    let file = File::create(path).expect("failed to create log file"); // TODO: dont overwrite pre-existing
     let mut builder = Builder::from_env(Env::default());
     builder
         .target(Target::Pipe(Box::new(file)))
-        .filter_level(LevelFilter::Info)
+        .filter_level(args.log_level.unwrap_or(settings.log_level))
         .init();
     Ok(())
 }
 
-/// Creates a pid file with format /tmp/ferristatus-XXXXXX.pid
-/// TODO: double-check and make sure that the PID file is being auto-deleted.
-pub fn create_pid_file() -> anyhow::Result<PidFile> {
-    match PidFile::new(format!("/tmp/ferristatus-{}.pid", {
-        let mut rng = rand::rng();
-        (0..6)
-            .map(|_| rng.random_range(0..10).to_string())
-            .collect::<String>()
-    })) {
+/// Creates the pid file of the instance called `instance`, failing if that
+/// instance is already running. It's removed again when dropped.
+pub fn create_pid_file(instance: &str) -> anyhow::Result<PidFile> {
+    let path = pid_path(instance);
+    if let Some(dir) = path.parent() {
+        DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+
+    match PidFile::new(&path) {
         Ok(v) => Ok(v),
         Err(e) => match e.kind() {
-            io::ErrorKind::AddrInUse => Ok(create_pid_file()?),
-            _ => Err(e.into()),
+            io::ErrorKind::AddrInUse => anyhow::bail!(
+                "the instance {} is already running, use --instance to start another one",
+                instance
+            ),
+            _ => Err(e).with_context(|| format!("failed to create {}", path.display())),
         },
     }
 }
 
-/// Update every component as needed.
-pub fn update_check_all(components: &mut MutexGuard<'_, ComponentVecType>) -> anyhow::Result<()> {
+/// Update every component as needed, returning whether any cache changed.
+pub fn update_check_all(components: &mut MutexGuard<'_, ComponentVecType>) -> anyhow::Result<bool> {
+    let mut dirty = false;
     for c in components.iter_mut() {
-        c.lock()
+        dirty |= c
+            .lock()
             .map_err(MyErrors::from_poison_error)?
             .update_maybe()?;
     }
 
-    Ok(())
+    Ok(dirty)
 }
 
-/// Update components with a corresponding signal value.
+/// Update components with a corresponding signal value, returning whether any
+/// cache changed.
 pub fn update_matching_signal(
     signal: u32,
     components: &mut MutexGuard<'_, ComponentVecType>,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<bool> {
+    let mut dirty = false;
     for c in components.iter() {
         let mut c_guard: MutexGuard<_> = c.lock().map_err(MyErrors::from_poison_error)?;
 
+        if let Some(action) = c_guard.get_signal_action(signal)?.map(|a| a.to_string()) {
+            c_guard.run_action(&action, &[])?;
+            dirty |= c_guard.update_and_compare()?;
+            continue;
+        }
+
         if c_guard.get_signal_value()? == Some(&signal) {
-            c_guard.update()?;
+            dirty |= c_guard.update_and_compare()?;
             continue;
         }
     }
 
-    Ok(())
+    Ok(dirty)
 }
 
 /// Collect the cache from every component and print it to stdout.
@@ -103,57 +152,266 @@ pub fn collect_all_cache(components: &MutexGuard<'_, ComponentVecType>) -> anyho
     Ok(line)
 }
 
-pub fn update_and_print(components: &Arc<Mutex<ComponentVecType>>) -> anyhow::Result<()> {
+pub fn update_and_print(
+    components: &Arc<Mutex<ComponentVecType>>,
+    output: &mut Output,
+) -> anyhow::Result<()> {
     // lock the components
     let mut components_guard: MutexGuard<'_, ComponentVecType> =
         components.lock().map_err(MyErrors::from_poison_error)?;
 
     // update check all
-    update_check_all(&mut components_guard).context("failed to update all components")?;
+    let dirty =
+        update_check_all(&mut components_guard).context("failed to update all components")?;
 
     // collect all and print
-    println!("{}", collect_all_cache(&components_guard)?);
+    if dirty || output.heartbeat_due() {
+        output.print(collect_all_cache(&components_guard)?);
+    }
+
+    Ok(())
+}
+
+/// Ask the running instances (or just `instance`) to refresh `component`
+/// through their control sockets. Each instance looks it up in its own config,
+/// so instances started with another config don't refresh the wrong one. Fails
+/// if no running instance could refresh it.
+pub fn refresh_running_instances(instance: Option<&str>, component: &str) -> anyhow::Result<()> {
+    let instances = running_instances(instance);
+    if let (Some(name), true) = (instance, instances.is_empty()) {
+        anyhow::bail!("no running instance called {}", name);
+    }
+
+    let request = Request::Refresh {
+        component: component.to_string(),
+    };
+    let mut errors: Vec<String> = Vec::new();
+    for name in &instances {
+        if let Err(e) = send_request(name, &request) {
+            errors.push(format!("instance {}: {:#}", name, e));
+        }
+    }
+    anyhow::ensure!(
+        errors.is_empty() || errors.len() < instances.len(),
+        "failed to refresh {}: {}",
+        component,
+        errors.join("; ")
+    );
+
+    Ok(())
+}
+
+/// Run an action on every component that supports it, then ask running
+/// instances to refresh those components immediately.
+pub fn run_action(args: &Args, action: &str, action_args: &[String]) -> anyhow::Result<()> {
+    let config = Config::new(args).context("failed to create config")?;
+
+    let mut handled: BTreeSet<&str> = BTreeSet::new();
+    for (c, name) in config.components.vec.iter().zip(&config.components.names) {
+        let mut c_guard: MutexGuard<_> = c.lock().map_err(MyErrors::from_poison_error)?;
+
+        if c_guard.run_action(action, action_args)? {
+            handled.insert(name);
+        }
+    }
+
+    anyhow::ensure!(
+        !handled.is_empty(),
+        "no component supports the action: {}",
+        action
+    );
+    // the action is done, so failing to refresh is only worth a warning
+    for name in handled {
+        if let Err(e) = refresh_running_instances(args.instance.as_deref(), name) {
+            eprintln!("warning: {:#}", e);
+        }
+    }
 
     Ok(())
 }
 
-macro_rules! sleep_for_duration {
-    ($interval:expr) => {
-        thread::sleep(Duration::from_millis($interval))
+/// Ask running instances to refresh the components called `component`
+/// immediately.
+pub fn send_signal(args: &Args, component: &str) -> anyhow::Result<()> {
+    refresh_running_instances(args.instance.as_deref(), component)
+}
+
+/// The earliest instant at which any component is due for an update.
+pub fn next_deadline(
+    components: &MutexGuard<'_, ComponentVecType>,
+) -> anyhow::Result<Option<Instant>> {
+    let deadlines: Vec<Option<Instant>> = components
+        .iter()
+        .map(|c| c.lock().map_err(MyErrors::from_poison_error)?.next_update())
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(deadlines.into_iter().flatten().min())
+}
+
+/// Block until a signal value arrives (returning it) or the deadline passes
+/// (returning None).
+pub fn wait_for_signal(
+    receiver: &Receiver<u32>,
+    deadline: Option<Instant>,
+) -> anyhow::Result<Option<u32>> {
+    let result = match deadline {
+        Some(d) => receiver.recv_timeout(d.saturating_duration_since(Instant::now())),
+        None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
     };
+
+    match result {
+        Ok(signal) => Ok(Some(signal)),
+        Err(RecvTimeoutError::Timeout) => Ok(None),
+        Err(RecvTimeoutError::Disconnected) => anyhow::bail!("signal channel disconnected"),
+    }
+}
+
+/// Why the main loop stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopExit {
+    Quit,
+    /// Restart with a freshly read config.
+    Reload,
+}
+
+/// Update and print whenever a component is due or a signal arrives, sleeping
+/// in between. Stops after `max_iter` iterations, if given, or when asked to
+/// through the control socket.
+pub fn run_loop(
+    components: &Arc<Mutex<ComponentVecType>>,
+    receiver: &Receiver<u32>,
+    control: Option<&Control>,
+    output: &mut Output,
+    max_iter: Option<u32>,
+) -> anyhow::Result<LoopExit> {
+    // initial render
+    update_and_print(components, output)?;
+
+    let mut iter = 1;
+    while max_iter.is_none_or(|n| iter < n) {
+        let deadline = next_deadline(&components.lock().map_err(MyErrors::from_poison_error)?)?;
+        let deadline = match (deadline, output.next_heartbeat()) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+
+        match wait_for_signal(receiver, deadline)? {
+            // a background thread has new output, update whatever is due
+            Some(WAKE) => update_and_print(components, output)?,
+            // a client of the control socket is waiting for a reply
+            Some(CONTROL) => {
+                if let Some(exit) = control
+                    .map(|c| c.handle_pending(components, output))
+                    .transpose()?
+                    .flatten()
+                {
+                    return Ok(exit);
+                }
+            }
+            Some(signal) => {
+                // logging, at debug since the clock and watcher threads send
+                // these too, e.g. every second for a Time with %S
+                log::debug!("updating for signal: {}", signal);
+
+                // lock the components
+                let mut components_guard: MutexGuard<'_, ComponentVecType> =
+                    components.lock().map_err(MyErrors::from_poison_error)?;
+
+                // update only the corresponding component
+                if update_matching_signal(signal, &mut components_guard)? {
+                    // collect all and print
+                    output.print(collect_all_cache(&components_guard)?);
+                }
+            }
+            None => update_and_print(components, output)?,
+        }
+
+        iter += 1;
+    }
+
+    Ok(LoopExit::Quit)
 }
 
 /// The main body of the program.
 pub fn run_program(args: Args, max_iter: Option<u32>) -> anyhow::Result<()> {
-    // set up logging
-    init_logger()?;
+    // set up logging, so that warnings about the components are logged too
+    let settings = Config::read_settings(&args).context("failed to create config")?;
+    init_logger(&args, &settings)?;
 
     // parse config
     let config = Config::new(&args).context("failed to create config")?;
+    if config.settings.check_interval.is_some() {
+        log::warn!("settings.check_interval is no longer used and can be removed");
+    }
 
     // get components
     let components: Arc<Mutex<ComponentVecType>> = Arc::new(Mutex::new(config.components.vec));
-    let components_for_signal: Arc<Mutex<ComponentVecType>> = Arc::clone(&components);
 
-    // create pid file
-    let _pidfile = create_pid_file()?;
+    // create pid file, after removing those of instances that are gone
+    remove_stale_files();
+    let pidfile = create_pid_file(args.instance_name())?;
 
-    // create signal watcher thread
-    spawn_signal_responder_thread(components_for_signal)?;
+    // forward RT signals, control requests and watched sysfs changes to the main loop
+    let (signal_sender, signal_receiver) = mpsc::channel();
+    signals_watch(signal_sender.clone())?;
+    let control = match Control::spawn(
+        &socket_path(args.instance_name()),
+        config.components.names,
+        args.config_path.clone(),
+        signal_sender.clone(),
+    ) {
+        Ok(c) => Some(c),
+        Err(e) => {
+            log::warn!("control socket disabled: {:#}", e);
+            None
+        }
+    };
+    spawn_watcher_threads(
+        &components.lock().map_err(MyErrors::from_poison_error)?,
+        signal_sender,
+    )?;
 
     // run until terminated
-    match max_iter {
-        None => loop {
-            update_and_print(&components)?;
-            sleep_for_duration!(config.settings.check_interval);
-        },
-        Some(n) => {
-            for _ in 0..n {
-                update_and_print(&components)?;
-                sleep_for_duration!(config.settings.check_interval);
+    let mut output = Output::new(&config.settings).context("failed to set up output")?;
+    let mut pidfile = Some(pidfile);
+    loop {
+        match run_loop(
+            &components,
+            &signal_receiver,
+            control.as_ref(),
+            &mut output,
+            max_iter,
+        )? {
+            LoopExit::Quit => return Ok(()),
+            LoopExit::Reload => {
+                // start over in a fresh process, so no threads or child processes
+                // of the old components are left behind. The new process
+                // creates its own pid file, with the same pid.
+                log::info!("reloading");
+                drop(pidfile.take());
+                let e = restart();
+                pidfile = Some(create_pid_file(args.instance_name())?);
+
+                // e.g. the binary was replaced, keep going with the old config
+                let e = e.context("failed to restart, keeping the current config");
+                log::error!("{:#}", e);
+                if let Some(c) = &control {
+                    c.reload_failed(e);
+                }
             }
         }
-    };
+    }
+}
 
-    Ok(())
+/// Replace this process with a fresh one with the same arguments, only
+/// returning if that fails.
+fn restart() -> anyhow::Error {
+    let exe = match env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => return e.into(),
+    };
+    process::Command::new(exe)
+        .args(env::args_os().skip(1))
+        .exec()
+        .into()
 }
@@ -1,10 +1,25 @@
 use clap::Parser;
-use ferristatus::{args::Args, run_program};
+use ferristatus::{
+    args::{Args, SubCommand},
+    control::run_ctl,
+    import::run_import,
+    run_action, run_program, send_signal,
+};
 
 fn main() -> anyhow::Result<()> {
     // parse args
     let args = Args::parse();
-    run_program(args, None)
+    match &args.command {
+        None => run_program(args, None),
+        Some(SubCommand::Action {
+            action,
+            args: action_args,
+        }) => run_action(&args, action, action_args),
+        Some(SubCommand::Timer { action }) => run_action(&args, &format!("timer_{}", action), &[]),
+        Some(SubCommand::Signal { component }) => send_signal(&args, component),
+        Some(SubCommand::Ctl { request }) => run_ctl(args.instance_name(), request),
+        Some(SubCommand::Import { format, file }) => run_import(format, file),
+    }
 }
 
 #[cfg(test)]
@@ -17,6 +32,8 @@ mod tests {
         // parse args
         let args = Args {
             config_path: "examples/config.yml".into(),
+            instance: Some("test".into()),
+            ..Args::default()
         };
         super::run_program(args, Some(2))
     }
@@ -0,0 +1,452 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{FileTypeExt, OpenOptionsExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use serde::Deserialize;
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{Atom, AtomEnum, ConnectionExt, PropMode, Window},
+    rust_connection::RustConnection,
+    wrapper::ConnectionExt as _,
+};
+
+use crate::config::Settings;
+
+/// Where the status line goes.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputBackend {
+    /// One line per update on stdout, for bars that read stdin.
+    Stdout,
+    /// The root window's name, like `xsetroot -name` (for dwm).
+    Xsetroot,
+    /// A file that is atomically rewritten on every update.
+    File {
+        path: PathBuf,
+        #[serde(default)]
+        format: SinkFormat,
+    },
+    /// A named pipe, written to whenever something is reading it.
+    Fifo {
+        path: PathBuf,
+        #[serde(default)]
+        format: SinkFormat,
+    },
+    /// A Unix socket that sends the latest line to every client that connects.
+    Socket {
+        path: PathBuf,
+        #[serde(default)]
+        format: SinkFormat,
+    },
+}
+
+/// The `output` setting: one backend or a list of them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum OutputSetting {
+    One(OutputBackend),
+    Many(Vec<OutputBackend>),
+}
+
+impl Default for OutputSetting {
+    fn default() -> Self {
+        OutputSetting::One(OutputBackend::Stdout)
+    }
+}
+
+impl OutputSetting {
+    pub fn backends(&self) -> &[OutputBackend] {
+        match self {
+            OutputSetting::One(b) => std::slice::from_ref(b),
+            OutputSetting::Many(v) => v,
+        }
+    }
+}
+
+/// How a sink formats the line.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SinkFormat {
+    /// The line as is.
+    #[default]
+    Plain,
+    /// A JSON object like `{"text": "..."}`, e.g. for waybar's custom modules.
+    Json,
+}
+
+impl SinkFormat {
+    fn apply(&self, line: &str) -> String {
+        match self {
+            SinkFormat::Plain => line.to_string(),
+            SinkFormat::Json => format!("{{\"text\":\"{}\"}}", escape_json(line)),
+        }
+    }
+}
+
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Something that displays the status line.
+pub trait Sink {
+    fn write(&mut self, line: &str) -> anyhow::Result<()>;
+}
+
+impl OutputBackend {
+    fn sink(&self) -> anyhow::Result<Box<dyn Sink>> {
+        Ok(match self {
+            OutputBackend::Stdout => Box::new(StdoutSink),
+            OutputBackend::Xsetroot => Box::new(XsetrootSink::default()),
+            OutputBackend::File { path, format } => Box::new(FileSink {
+                path: path.clone(),
+                format: *format,
+            }),
+            OutputBackend::Fifo { path, format } => Box::new(FifoSink::new(path, *format)?),
+            OutputBackend::Socket { path, format } => Box::new(SocketSink::new(path, *format)?),
+        })
+    }
+}
+
+/// Sends the composed status line to every configured sink, skipping lines
+/// identical to the previous one unless a heartbeat is due.
+pub struct Output {
+    sinks: Vec<Box<dyn Sink>>,
+    last_line: Option<String>,
+    last_printed: Option<Instant>,
+    force_refresh_interval: Option<Duration>,
+}
+
+impl Output {
+    pub fn new(settings: &Settings) -> anyhow::Result<Self> {
+        let sinks = settings
+            .output
+            .backends()
+            .iter()
+            .map(|b| b.sink())
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            sinks,
+            last_line: None,
+            last_printed: None,
+            force_refresh_interval: settings.force_refresh_interval.map(Duration::from_millis),
+        })
+    }
+
+    /// When the next heartbeat print is due, if `force_refresh_interval` is set.
+    pub fn next_heartbeat(&self) -> Option<Instant> {
+        let interval = self.force_refresh_interval?;
+        Some(match self.last_printed {
+            Some(t) => t + interval,
+            None => Instant::now(),
+        })
+    }
+
+    pub fn heartbeat_due(&self) -> bool {
+        self.next_heartbeat()
+            .is_some_and(|deadline| deadline <= Instant::now())
+    }
+
+    /// Print the line if it differs from the last one (or a heartbeat is due).
+    pub fn print(&mut self, line: String) {
+        if self.last_line.as_ref() == Some(&line) && !self.heartbeat_due() {
+            return;
+        }
+
+        let mut failed = false;
+        for sink in self.sinks.iter_mut() {
+            if let Err(e) = sink.write(&line) {
+                log::error!("failed to output status line: {:#}", e);
+                failed = true;
+            }
+        }
+
+        // on failure, forget the line so it's retried next time
+        self.last_line = (!failed).then_some(line);
+        self.last_printed = Some(Instant::now());
+    }
+}
+
+// stdout /////////////////////////////////////////////////////////////////////
+
+pub struct StdoutSink;
+
+impl Sink for StdoutSink {
+    fn write(&mut self, line: &str) -> anyhow::Result<()> {
+        println!("{}", line);
+        Ok(())
+    }
+}
+
+// file ///////////////////////////////////////////////////////////////////////
+
+/// Rewrites a file through a temporary file and a rename, so readers never
+/// see a partial line.
+pub struct FileSink {
+    path: PathBuf,
+    format: SinkFormat,
+}
+
+impl Sink for FileSink {
+    fn write(&mut self, line: &str) -> anyhow::Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+
+        fs::write(&tmp, format!("{}\n", self.format.apply(line)))
+            .with_context(|| format!("failed to write {}", Path::new(&tmp).display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("failed to replace {}", self.path.display()))
+    }
+}
+
+// fifo ///////////////////////////////////////////////////////////////////////
+
+/// Writes to a named pipe (created if needed), only while something reads it.
+pub struct FifoSink {
+    path: PathBuf,
+    format: SinkFormat,
+    pipe: Option<File>,
+}
+
+impl FifoSink {
+    fn new(path: &Path, format: SinkFormat) -> anyhow::Result<Self> {
+        match fs::metadata(path) {
+            Ok(m) if m.file_type().is_fifo() => {}
+            Ok(_) => anyhow::bail!("{} exists and isn't a FIFO", path.display()),
+            Err(_) => {
+                let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+                // SAFETY: c_path is a valid NUL-terminated string.
+                if unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) } != 0 {
+                    Err(io::Error::last_os_error())
+                        .with_context(|| format!("failed to create FIFO {}", path.display()))?;
+                }
+            }
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            format,
+            pipe: None,
+        })
+    }
+}
+
+impl Sink for FifoSink {
+    fn write(&mut self, line: &str) -> anyhow::Result<()> {
+        if self.pipe.is_none() {
+            // non-blocking, so this fails (ENXIO) instead of waiting for a reader
+            match OpenOptions::new()
+                .write(true)
+                .custom_flags(libc::O_NONBLOCK)
+                .open(&self.path)
+            {
+                Ok(pipe) => self.pipe = Some(pipe),
+                Err(e) if e.raw_os_error() == Some(libc::ENXIO) => return Ok(()),
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("failed to open {}", self.path.display()));
+                }
+            }
+        }
+        let Some(pipe) = &mut self.pipe else {
+            unreachable!("opened above");
+        };
+
+        match writeln!(pipe, "{}", self.format.apply(line)) {
+            Ok(()) => Ok(()),
+            // the reader went away (EPIPE) or isn't keeping up (EAGAIN), reopen next time
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::BrokenPipe | io::ErrorKind::WouldBlock
+                ) =>
+            {
+                self.pipe = None;
+                Ok(())
+            }
+            Err(e) => {
+                self.pipe = None;
+                Err(e).with_context(|| format!("failed to write to {}", self.path.display()))
+            }
+        }
+    }
+}
+
+// socket /////////////////////////////////////////////////////////////////////
+
+/// Listens on a Unix socket and sends the latest line to every client that
+/// connects, then closes the connection. The socket is removed when dropped.
+pub struct SocketSink {
+    path: PathBuf,
+    format: SinkFormat,
+    latest: Arc<Mutex<String>>,
+}
+
+impl SocketSink {
+    fn new(path: &Path, format: SinkFormat) -> anyhow::Result<Self> {
+        if fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket()) {
+            anyhow::ensure!(
+                UnixStream::connect(path).is_err(),
+                "another process is listening on {}",
+                path.display()
+            );
+            // left behind by an earlier run
+            fs::remove_file(path)
+                .with_context(|| format!("failed to remove old socket {}", path.display()))?;
+        }
+        let listener = UnixListener::bind(path)
+            .with_context(|| format!("failed to listen on {}", path.display()))?;
+
+        let latest: Arc<Mutex<String>> = Arc::default();
+        let thread_latest = Arc::clone(&latest);
+        thread::Builder::new()
+            .name("output-socket".into())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else {
+                        continue;
+                    };
+                    let line = match thread_latest.lock() {
+                        Ok(l) => l.clone(),
+                        Err(_) => return,
+                    };
+                    let _ = writeln!(stream, "{}", line);
+                }
+            })
+            .context("failed to spawn socket thread")?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            format,
+            latest,
+        })
+    }
+}
+
+impl Drop for SocketSink {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl Sink for SocketSink {
+    fn write(&mut self, line: &str) -> anyhow::Result<()> {
+        let mut latest = self
+            .latest
+            .lock()
+            .map_err(|_| anyhow::anyhow!("socket thread panicked"))?;
+        *latest = self.format.apply(line);
+        Ok(())
+    }
+}
+
+// xsetroot ///////////////////////////////////////////////////////////////////
+
+/// Sets WM_NAME on the root window of $DISPLAY, connecting (again) as needed,
+/// e.g. after the X server restarts.
+#[derive(Default)]
+pub struct XsetrootSink {
+    /// The connection, root window and UTF8_STRING atom.
+    connection: Option<(RustConnection, Window, Atom)>,
+}
+
+impl XsetrootSink {
+    fn connect() -> anyhow::Result<(RustConnection, Window, Atom)> {
+        let (conn, screen) = x11rb::connect(None).context("failed to connect to the X server")?;
+        let root = conn
+            .setup()
+            .roots
+            .get(screen)
+            .context("no such X screen")?
+            .root;
+        let utf8_string = conn.intern_atom(false, b"UTF8_STRING")?.reply()?.atom;
+        log::info!("connected to the X server");
+        Ok((conn, root, utf8_string))
+    }
+
+    fn set_name(
+        conn: &RustConnection,
+        root: Window,
+        utf8_string: Atom,
+        line: &str,
+    ) -> anyhow::Result<()> {
+        conn.change_property8(
+            PropMode::REPLACE,
+            root,
+            AtomEnum::WM_NAME,
+            utf8_string,
+            line.as_bytes(),
+        )?
+        .check()?;
+        conn.flush()?;
+        Ok(())
+    }
+}
+
+impl Sink for XsetrootSink {
+    fn write(&mut self, line: &str) -> anyhow::Result<()> {
+        if self.connection.is_none() {
+            self.connection = Some(Self::connect()?);
+        }
+        let Some((conn, root, utf8_string)) = &self.connection else {
+            unreachable!("connected above");
+        };
+
+        let result = Self::set_name(conn, *root, *utf8_string, line);
+        if result.is_err() {
+            // reconnect on the next line
+            self.connection = None;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_json() {
+        assert_eq!(
+            SinkFormat::Json.apply(r#" "a" \ b"#),
+            r#"{"text":" \"a\" \\ b"}"#
+        );
+        assert_eq!(SinkFormat::Json.apply("\t"), r#"{"text":"\u0009"}"#);
+    }
+
+    /// Needs an X server, e.g. `xvfb-run cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn xsetroot_sets_root_name() -> anyhow::Result<()> {
+        let mut sink = XsetrootSink::default();
+        sink.write("ferristatus test")?;
+
+        let (conn, root, _) = XsetrootSink::connect()?;
+        let reply = conn
+            .get_property(false, root, AtomEnum::WM_NAME, AtomEnum::ANY, 0, 1024)?
+            .reply()?;
+        assert_eq!(reply.value, b"ferristatus test");
+        Ok(())
+    }
+}
@@ -1,9 +1,19 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
 use anyhow::Context;
 use itertools::Itertools;
+use log::LevelFilter;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
 
 pub fn sort_levels(levels: &mut Option<Vec<(i32, String)>>) {
     if let Some(lvls) = levels {
-        lvls.sort_by(|a, b| a.0.cmp(&b.0));
+        lvls.sort_by_key(|a| a.0);
     }
 }
 
@@ -19,6 +29,78 @@ pub fn find_current_level<'a>(
         .context("failed to find_current_level")
 }
 
+/// Format a duration like "1h 33m", dropping the trailing seconds.
+pub fn format_duration_trimmed(duration: Duration) -> String {
+    let visual_time = &humantime::format_duration(duration).to_string();
+    static RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"\s[0-9]+s$").expect("could not build regex"));
+    RE.replace(visual_time, "").to_string()
+}
+
+/// Deserialize a human-readable duration like "25m" or "1h 30m".
+pub fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    humantime::parse_duration(&s).map_err(serde::de::Error::custom)
+}
+
+/// Deserialize a log level like "info" or "debug".
+pub fn deserialize_log_level<'de, D>(deserializer: D) -> Result<LevelFilter, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(serde::de::Error::custom)
+}
+
+/// Read a small sysfs attribute file, trimmed.
+pub fn read_sysfs_attr(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+/// Read a numeric sysfs attribute file.
+pub fn read_sysfs_num(path: &Path) -> Option<f64> {
+    read_sysfs_attr(path)?.parse().ok()
+}
+
+/// A `path` setting that accepts a single path, a list of paths, or "auto".
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum PathsSetting {
+    One(PathBuf),
+    Many(Vec<PathBuf>),
+}
+
+impl PathsSetting {
+    /// Whether the setting is the literal "auto", meaning "discover the devices".
+    pub fn is_auto(&self) -> bool {
+        matches!(self, PathsSetting::One(p) if p.as_os_str() == "auto")
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        match self {
+            PathsSetting::One(p) => std::slice::from_ref(p),
+            PathsSetting::Many(v) => v,
+        }
+    }
+}
+
+/// List the entries of a sysfs class directory whose name starts with `prefix`, sorted.
+pub fn list_sysfs_class(class: &str, prefix: &str) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = match fs::read_dir(class) {
+        Ok(dir) => dir
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().starts_with(prefix))
+            .map(|e| e.path())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    entries.sort();
+    entries
+}
+
 #[macro_export]
 macro_rules! deserialize_value {
     ( $value:tt ) => {{
@@ -98,6 +180,15 @@ macro_rules! impl_component_methods {
 
 #[macro_export]
 macro_rules! apply_strfmt {
+    ( $template:expr, extra: $extra:expr, $($key:expr => $value:expr),* $(,)? ) => {{
+        let mut vars: std::collections::HashMap<String, String> = std::collections::HashMap::from([
+            $(
+                ($key.to_owned(), $value),
+            )*
+        ]);
+        vars.extend($extra);
+        Ok(Some(strfmt::strfmt($template, &vars)?))
+    }};
     ( $template:expr, $($key:expr => $value:expr),* $(,)? ) => {{
         let vars: std::collections::HashMap<String, String> = std::collections::HashMap::from([
             $(
@@ -0,0 +1,301 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    mem,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    path::PathBuf,
+    sync::{MutexGuard, mpsc::Sender},
+    thread,
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Local, TimeDelta, TimeZone, Timelike};
+use inotify::{Inotify, WatchDescriptor, WatchMask};
+
+use crate::{components::ComponentVecType, errors::MyErrors};
+
+/// What a component in watch mode wants to be notified about.
+#[derive(Debug, Clone)]
+pub struct WatchTargets {
+    /// Sysfs device directories, e.g. /sys/class/backlight/intel_backlight.
+    pub devices: Vec<PathBuf>,
+    /// File inside each device to watch with inotify, e.g. "brightness".
+    pub file: &'static str,
+    /// Kernel subsystem whose uevents for these devices trigger an update.
+    pub subsystem: &'static str,
+}
+
+/// Wall-clock boundaries a component wants to be updated at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ClockGranularity {
+    Second,
+    Minute,
+    Hour,
+    Day,
+}
+
+impl ClockGranularity {
+    fn unit(&self) -> TimeDelta {
+        match self {
+            ClockGranularity::Second => TimeDelta::seconds(1),
+            ClockGranularity::Minute => TimeDelta::minutes(1),
+            ClockGranularity::Hour => TimeDelta::hours(1),
+            ClockGranularity::Day => TimeDelta::days(1),
+        }
+    }
+
+    /// The first boundary strictly after `now`.
+    pub fn next_boundary(&self, now: DateTime<Local>) -> DateTime<Local> {
+        let naive = now.naive_local();
+        let truncated = match self {
+            ClockGranularity::Second => naive.with_nanosecond(0),
+            ClockGranularity::Minute => naive.with_nanosecond(0).and_then(|t| t.with_second(0)),
+            ClockGranularity::Hour => naive
+                .with_nanosecond(0)
+                .and_then(|t| t.with_second(0))
+                .and_then(|t| t.with_minute(0)),
+            ClockGranularity::Day => naive.date().and_hms_opt(0, 0, 0),
+        }
+        .unwrap_or(naive);
+
+        // a local time can be skipped by a DST change, fall back to a plain offset
+        Local
+            .from_local_datetime(&(truncated + self.unit()))
+            .earliest()
+            .unwrap_or(now + self.unit())
+    }
+}
+
+/// Spawn threads that send a component's signal value through `sender` whenever
+/// one of its watched sysfs files changes or the kernel emits a uevent for it,
+/// along with each component's own background threads.
+pub fn spawn_watcher_threads(
+    components: &MutexGuard<'_, ComponentVecType>,
+    sender: Sender<u32>,
+) -> anyhow::Result<()> {
+    // (signal, targets) for every component in watch mode
+    let mut watched: Vec<(u32, WatchTargets)> = Vec::new();
+    for c in components.iter() {
+        let mut c_guard: MutexGuard<_> = c.lock().map_err(MyErrors::from_poison_error)?;
+        c_guard.spawn_background(sender.clone())?;
+        let Some(signal) = c_guard.get_signal_value()? else {
+            continue;
+        };
+        if let Some(targets) = c_guard.get_watch_targets()? {
+            watched.push((*signal, targets));
+        }
+        if let Some(granularity) = c_guard.get_clock_granularity()? {
+            spawn_clock_thread(*signal, granularity, sender.clone())?;
+        }
+    }
+
+    if watched.is_empty() {
+        return Ok(());
+    }
+
+    // the components are still polled at their refresh_interval, so a watcher
+    // that can't be set up (e.g. no netlink socket in a container) isn't fatal
+    if let Err(e) = spawn_inotify_thread(&watched, sender.clone()) {
+        log::warn!(
+            "not watching with inotify, falling back to polling: {:#}",
+            e
+        );
+    }
+    if let Err(e) = spawn_uevent_thread(&watched, sender) {
+        log::warn!("not watching uevents, falling back to polling: {:#}", e);
+    }
+
+    Ok(())
+}
+
+fn spawn_inotify_thread(
+    watched: &[(u32, WatchTargets)],
+    sender: Sender<u32>,
+) -> anyhow::Result<()> {
+    let mut inotify = Inotify::init().context("failed to initialize inotify")?;
+    let mut signals: HashMap<WatchDescriptor, u32> = HashMap::new();
+
+    for (signal, targets) in watched {
+        for device in &targets.devices {
+            let path = device.join(targets.file);
+            match inotify
+                .watches()
+                .add(&path, WatchMask::MODIFY | WatchMask::CLOSE_WRITE)
+            {
+                Ok(wd) => {
+                    log::info!("watching {} with inotify", path.display());
+                    signals.insert(wd, *signal);
+                }
+                Err(e) => log::warn!("failed to watch {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    thread::spawn(move || -> anyhow::Result<()> {
+        let mut buffer = [0; 1024];
+        loop {
+            let events = inotify
+                .read_events_blocking(&mut buffer)
+                .context("failed to read inotify events")?;
+            for event in events {
+                if let Some(signal) = signals.get(&event.wd) {
+                    let _ = sender.send(*signal); // We don't care if the send fails
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Open a netlink socket subscribed to kernel uevents.
+fn open_uevent_socket() -> anyhow::Result<File> {
+    // SAFETY: plain socket(2) call, the returned fd is checked below.
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+            libc::NETLINK_KOBJECT_UEVENT,
+        )
+    };
+    anyhow::ensure!(
+        fd >= 0,
+        "failed to open netlink socket: {}",
+        std::io::Error::last_os_error()
+    );
+    // SAFETY: fd is a freshly opened socket that nothing else owns.
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // SAFETY: sockaddr_nl is plain old data, all zeroes is valid.
+    let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    addr.nl_groups = 1; // kernel uevents
+
+    // SAFETY: addr is a valid sockaddr_nl and the length matches it.
+    let ret = unsafe {
+        libc::bind(
+            fd.as_raw_fd(),
+            &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        )
+    };
+    anyhow::ensure!(
+        ret == 0,
+        "failed to bind netlink socket: {}",
+        std::io::Error::last_os_error()
+    );
+
+    Ok(File::from(fd))
+}
+
+fn spawn_uevent_thread(watched: &[(u32, WatchTargets)], sender: Sender<u32>) -> anyhow::Result<()> {
+    // (subsystem, device name, signal)
+    let matches: Vec<(&'static str, String, u32)> = watched
+        .iter()
+        .flat_map(|(signal, targets)| {
+            targets.devices.iter().filter_map(move |d| {
+                let name = d.file_name()?.to_string_lossy().to_string();
+                Some((targets.subsystem, name, *signal))
+            })
+        })
+        .collect();
+
+    let mut socket = open_uevent_socket()?;
+
+    thread::spawn(move || -> anyhow::Result<()> {
+        let mut buffer = [0; 4096];
+        loop {
+            let len = socket
+                .read(&mut buffer)
+                .context("failed to read from netlink socket")?;
+
+            // e.g. "change@/devices/...\0ACTION=change\0DEVPATH=...\0SUBSYSTEM=power_supply\0..."
+            let mut subsystem = None;
+            let mut device = None;
+            for field in buffer[..len].split(|b| *b == 0) {
+                let field = String::from_utf8_lossy(field);
+                if let Some(v) = field.strip_prefix("SUBSYSTEM=") {
+                    subsystem = Some(v.to_string());
+                } else if let Some(v) = field.strip_prefix("DEVPATH=") {
+                    device = v.rsplit('/').next().map(|s| s.to_string());
+                }
+            }
+
+            for (s, d, signal) in &matches {
+                if subsystem.as_deref() == Some(s) && device.as_deref() == Some(d.as_str()) {
+                    log::debug!("received uevent for {}/{}", s, d);
+                    let _ = sender.send(*signal); // We don't care if the send fails
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Arm `timer` to fire once at the wall-clock time `at`, or as soon as the
+/// system clock is changed.
+fn arm_timerfd(timer: &OwnedFd, at: DateTime<Local>) -> anyhow::Result<()> {
+    let spec = libc::itimerspec {
+        it_interval: libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        },
+        it_value: libc::timespec {
+            tv_sec: at.timestamp() as libc::time_t,
+            tv_nsec: at.timestamp_subsec_nanos() as libc::c_long,
+        },
+    };
+    // SAFETY: timer is a valid timerfd and spec outlives the call.
+    let ret = unsafe {
+        libc::timerfd_settime(
+            timer.as_raw_fd(),
+            libc::TFD_TIMER_ABSTIME | libc::TFD_TIMER_CANCEL_ON_SET,
+            &spec,
+            std::ptr::null_mut(),
+        )
+    };
+    anyhow::ensure!(
+        ret == 0,
+        "failed to arm timerfd: {}",
+        std::io::Error::last_os_error()
+    );
+    Ok(())
+}
+
+/// Spawn a thread that sends `signal` at every wall-clock boundary. A
+/// CLOCK_REALTIME timer is used so it still fires on time after a suspend, and
+/// wakes up early when the system clock is changed.
+fn spawn_clock_thread(
+    signal: u32,
+    granularity: ClockGranularity,
+    sender: Sender<u32>,
+) -> anyhow::Result<()> {
+    // SAFETY: plain timerfd_create(2) call, the returned fd is checked below.
+    let fd = unsafe { libc::timerfd_create(libc::CLOCK_REALTIME, libc::TFD_CLOEXEC) };
+    anyhow::ensure!(
+        fd >= 0,
+        "failed to create timerfd: {}",
+        std::io::Error::last_os_error()
+    );
+    // SAFETY: fd is a freshly opened timerfd that nothing else owns.
+    let timer = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    thread::spawn(move || -> anyhow::Result<()> {
+        let mut file = File::from(timer.try_clone()?);
+        let mut expirations = [0; 8];
+        loop {
+            arm_timerfd(&timer, granularity.next_boundary(Local::now()))?;
+
+            // fails with ECANCELED when the clock was changed, update either way
+            if let Err(e) = file.read(&mut expirations) {
+                log::info!("system clock changed ({}), updating", e);
+            }
+
+            let _ = sender.send(signal); // We don't care if the send fails
+        }
+    });
+
+    Ok(())
+}